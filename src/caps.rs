@@ -0,0 +1,157 @@
+use std::{
+    fmt::{Debug, Display},
+    ops::{BitAnd, BitOr},
+};
+
+/// What a key allows to do with its entry.
+///
+/// Keys of entries have every capability.
+/// Weaker keys are made with `restrict`, as aliases of the entry.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Caps(u8);
+
+impl Caps {
+    pub const NONE: Caps = Caps(0);
+    pub const READ: Caps = Caps(1);
+    /// Allows to take the value, unless a `ForgottenKey` owns the entry.
+    pub const TAKE: Caps = Caps(1 << 1);
+    /// Allows to free the entry, unless a `ForgottenKey` owns it.
+    pub const FREE: Caps = Caps(1 << 2);
+    /// Allows to make other keys of the entry, with at most the same capabilities.
+    pub const DERIVE: Caps = Caps(1 << 3);
+    /// Allows to mutate the value in place, in stores which support it.
+    pub const WRITE: Caps = Caps(1 << 4);
+    pub const ALL: Caps = Caps(0b1_1111);
+
+    #[inline]
+    pub fn contains(self, caps: Caps) -> bool {
+        self.0 & caps.0 == caps.0
+    }
+
+    #[inline]
+    pub(crate) fn check(self, required: Caps) -> Result<(), PermissionDenied> {
+        if self.contains(required) {
+            Ok(())
+        } else {
+            Err(PermissionDenied::new(required))
+        }
+    }
+}
+
+impl BitOr for Caps {
+    type Output = Caps;
+
+    #[inline]
+    fn bitor(self, rhs: Caps) -> Caps {
+        Caps(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Caps {
+    type Output = Caps;
+
+    #[inline]
+    fn bitand(self, rhs: Caps) -> Caps {
+        Caps(self.0 & rhs.0)
+    }
+}
+
+impl Debug for Caps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = [
+            (Caps::READ, "READ"),
+            (Caps::TAKE, "TAKE"),
+            (Caps::FREE, "FREE"),
+            (Caps::DERIVE, "DERIVE"),
+            (Caps::WRITE, "WRITE"),
+        ];
+        let mut set = f.debug_set();
+
+        for (caps, name) in names.iter() {
+            if self.contains(*caps) {
+                set.entry(&format_args!("{}", name));
+            }
+        }
+
+        set.finish()
+    }
+}
+
+/// Returned when a key lacks the capabilities an operation requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermissionDenied {
+    required: Caps,
+}
+
+impl PermissionDenied {
+    #[inline]
+    pub(crate) fn new(required: Caps) -> Self {
+        Self { required }
+    }
+
+    #[inline]
+    pub fn required(&self) -> Caps {
+        self.required
+    }
+}
+
+impl Display for PermissionDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "permission denied, {:?} required", self.required)
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+/// Returned when an operation can not be done with a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessError {
+    /// The entry does not exist, e.g. it was freed, or the alias was revoked.
+    NotFound,
+    Denied(PermissionDenied),
+    /// The entry is owned by a `ForgottenKey` or `CountedForgottenKey`s,
+    /// through which alone it can be freed or taken.
+    Owned,
+    /// The value of the entry is given to its finalizer, so it can not be shared.
+    Finalized,
+}
+
+impl AccessError {
+    /// The capabilities the key lacks, if that is why the operation failed.
+    #[inline]
+    pub fn required(&self) -> Option<Caps> {
+        match self {
+            AccessError::Denied(e) => Some(e.required()),
+            _ => None,
+        }
+    }
+}
+
+impl From<PermissionDenied> for AccessError {
+    #[inline]
+    fn from(e: PermissionDenied) -> Self {
+        AccessError::Denied(e)
+    }
+}
+
+impl Display for AccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessError::NotFound => write!(f, "no such entry"),
+            AccessError::Owned => write!(f, "entry is owned by another key"),
+            AccessError::Finalized => {
+                write!(f, "value of entry with a finalizer can not be shared")
+            }
+            AccessError::Denied(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for AccessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AccessError::Denied(e) => Some(e),
+            _ => None,
+        }
+    }
+}
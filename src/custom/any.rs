@@ -0,0 +1,132 @@
+use std::{
+    any::{Any, TypeId},
+    hash::BuildHasher,
+    rc::Rc,
+};
+
+use num::{traits::ops::overflowing::OverflowingAdd, One, Zero};
+
+use crate::Caps;
+
+use super::{Forgotten, ForgottenRefCell};
+
+/// A key of a `Forgotten<K, dyn Any>`, which remembers the type of its value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnyKey<K> {
+    key: K,
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+impl<K> AnyKey<K> {
+    #[inline]
+    fn new<U: Any>(key: K) -> Self {
+        Self {
+            key,
+            type_id: TypeId::of::<U>(),
+            type_name: std::any::type_name::<U>(),
+        }
+    }
+
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    #[inline]
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    #[inline]
+    pub fn is<U: Any>(&self) -> bool {
+        self.type_id == TypeId::of::<U>()
+    }
+}
+
+impl<K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One, S: BuildHasher>
+    Forgotten<K, dyn Any, Rc<dyn Any>, S>
+{
+    #[inline]
+    pub fn forget_any<U: Any>(&mut self, v: U) -> AnyKey<K> {
+        AnyKey::new::<U>(self.insert_as(Rc::new(v), std::any::type_name::<U>()))
+    }
+
+    /// Returns `None` if there is no such entry or its value is not a `U`.
+    #[inline]
+    pub fn try_get_as<U: Any>(&self, k: &K) -> Option<Rc<U>> {
+        Rc::clone(self.try_ref(k)?).downcast().ok()
+    }
+
+    /// Returns `None` if there is no such entry, `k` lacks [`Caps::TAKE`],
+    /// or its value is not a `U`. In the latter case the entry is left in place.
+    #[inline]
+    pub fn try_take_as<U: Any>(&mut self, k: &K) -> Option<Rc<U>> {
+        if !self.peek(k)?.is::<U>() {
+            return None;
+        }
+
+        let (_, v) = self.remove_checked(k, Caps::TAKE).ok()?;
+        v.downcast().ok()
+    }
+
+    /// Like [`try_get_as`](Self::try_get_as), also returning `None` if `k` is not a key of a `U`.
+    #[inline]
+    pub fn try_get_any<U: Any>(&self, k: &AnyKey<K>) -> Option<Rc<U>> {
+        if !k.is::<U>() {
+            return None;
+        }
+
+        self.try_get_as(k.key())
+    }
+
+    /// Like [`try_take_as`](Self::try_take_as), also returning `None` if `k` is not a key of a `U`.
+    #[inline]
+    pub fn try_take_any<U: Any>(&mut self, k: &AnyKey<K>) -> Option<Rc<U>> {
+        if !k.is::<U>() {
+            return None;
+        }
+
+        self.try_take_as(k.key())
+    }
+}
+
+impl<K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One, S: BuildHasher>
+    ForgottenRefCell<K, dyn Any, Rc<dyn Any>, S>
+{
+    #[inline]
+    pub fn forget_any<U: Any>(&self, v: U) -> AnyKey<K> {
+        let k = self.with_mut(|fg| fg.insert_as(Rc::new(v), std::any::type_name::<U>()));
+        AnyKey::new::<U>(k)
+    }
+
+    #[inline]
+    pub fn try_get_as<U: Any>(&self, k: &K) -> Option<Rc<U>> {
+        self.try_get(k)?.downcast().ok()
+    }
+
+    #[inline]
+    pub fn try_take_as<U: Any>(&self, k: &K) -> Option<Rc<U>> {
+        self.with_mut(|fg| fg.try_take_as(k))
+    }
+
+    #[inline]
+    pub fn try_get_any<U: Any>(&self, k: &AnyKey<K>) -> Option<Rc<U>> {
+        self.0.borrow().try_get_any(k)
+    }
+
+    #[inline]
+    pub fn try_take_any<U: Any>(&self, k: &AnyKey<K>) -> Option<Rc<U>> {
+        self.with_mut(|fg| fg.try_take_any(k))
+    }
+}
@@ -0,0 +1,218 @@
+use std::{hash::BuildHasher, ops::DerefMut};
+
+use num::{traits::ops::overflowing::OverflowingAdd, One, Zero};
+
+use crate::QuotaExceeded;
+
+use super::{Forgotten, ForgottenPtr};
+
+/// A view into a single key of a [`Forgotten`],
+/// obtained with [`Forgotten::entry`].
+///
+/// Inserting and removing through it is the same as
+/// [`forget_ptr_at`](Forgotten::forget_ptr_at) and [`try_take`](Forgotten::try_take).
+pub enum Entry<'a, K, T: ?Sized, P, S> {
+    Occupied(OccupiedEntry<'a, K, T, P, S>),
+    Vacant(VacantEntry<'a, K, T, P, S>),
+}
+
+pub struct OccupiedEntry<'a, K, T: ?Sized, P, S> {
+    pub(super) fg: &'a mut Forgotten<K, T, P, S>,
+    pub(super) key: K,
+}
+
+pub struct VacantEntry<'a, K, T: ?Sized, P, S> {
+    pub(super) fg: &'a mut Forgotten<K, T, P, S>,
+    pub(super) key: K,
+}
+
+impl<
+        'a,
+        K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One,
+        T: ?Sized,
+        P: ForgottenPtr<Target = T>,
+        S: BuildHasher,
+    > Entry<'a, K, T, P, S>
+{
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(e) => e.key(),
+            Entry::Vacant(e) => e.key(),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert(self, v: T) -> &'a mut P
+    where
+        T: Sized,
+    {
+        self.or_insert_ptr_with(|| P::new(v))
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> T>(self, f: F) -> &'a mut P
+    where
+        T: Sized,
+    {
+        self.or_insert_ptr_with(|| P::new(f()))
+    }
+
+    #[inline]
+    pub fn or_insert_ptr_with<F: FnOnce() -> P>(self, f: F) -> &'a mut P {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert_ptr(f()),
+        }
+    }
+
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut P)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(e) = &mut self {
+            f(e.get_mut());
+        }
+        self
+    }
+
+    /// Removes the value if the entry is occupied.
+    #[inline]
+    pub fn remove(self) -> Option<P> {
+        match self {
+            Entry::Occupied(e) => Some(e.remove()),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// Sets the value of the entry, returning the previous one if any.
+    #[inline]
+    pub fn insert(self, v: T) -> Option<P>
+    where
+        T: Sized,
+    {
+        self.insert_ptr(P::new(v))
+    }
+
+    #[inline]
+    pub fn insert_ptr(self, v: P) -> Option<P> {
+        match self {
+            Entry::Occupied(mut e) => Some(e.insert_ptr(v)),
+            Entry::Vacant(e) => {
+                e.insert_ptr(v);
+                None
+            }
+        }
+    }
+}
+
+impl<
+        'a,
+        K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One,
+        T: ?Sized,
+        P: ForgottenPtr<Target = T>,
+        S: BuildHasher,
+    > OccupiedEntry<'a, K, T, P, S>
+{
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    #[inline]
+    pub fn get(&self) -> &P {
+        &self.fg.map[&self.key]
+    }
+
+    /// The pointer itself can be replaced.
+    /// Use [`value_mut`](Self::value_mut) to mutate the value in a `Box`.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut P {
+        self.fg.map.get_mut(&self.key).unwrap()
+    }
+
+    #[inline]
+    pub fn value_mut(&mut self) -> &mut T
+    where
+        P: DerefMut,
+    {
+        self.get_mut()
+    }
+
+    #[inline]
+    pub fn into_mut(self) -> &'a mut P {
+        self.fg.map.get_mut(&self.key).unwrap()
+    }
+
+    /// Replaces the value, keeping the rest of the entry.
+    #[inline]
+    pub fn insert(&mut self, v: T) -> P
+    where
+        T: Sized,
+    {
+        self.insert_ptr(P::new(v))
+    }
+
+    #[inline]
+    pub fn insert_ptr(&mut self, v: P) -> P {
+        self.try_insert_ptr(v).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [`insert`](Self::insert),
+    /// failing if the new value does not fit in the quota of the owner of the entry.
+    #[inline]
+    pub fn try_insert(&mut self, v: T) -> Result<P, QuotaExceeded>
+    where
+        T: Sized,
+    {
+        self.try_insert_ptr(P::new(v))
+    }
+
+    #[inline]
+    pub fn try_insert_ptr(&mut self, v: P) -> Result<P, QuotaExceeded> {
+        self.fg.replace(&self.key, v).map_err(|(e, _)| e)
+    }
+
+    #[inline]
+    pub fn remove(self) -> P {
+        self.fg.remove(&self.key).unwrap()
+    }
+
+    #[inline]
+    pub fn remove_entry(self) -> (K, P) {
+        let v = self.fg.remove(&self.key).unwrap();
+        (self.key, v)
+    }
+}
+
+impl<
+        'a,
+        K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One,
+        T: ?Sized,
+        P: ForgottenPtr<Target = T>,
+        S: BuildHasher,
+    > VacantEntry<'a, K, T, P, S>
+{
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    #[inline]
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    #[inline]
+    pub fn insert(self, v: T) -> &'a mut P
+    where
+        T: Sized,
+    {
+        self.insert_ptr(P::new(v))
+    }
+
+    #[inline]
+    pub fn insert_ptr(self, v: P) -> &'a mut P {
+        self.fg
+            .insert_at(self.key.clone(), v, std::any::type_name::<T>());
+        self.fg.map.get_mut(&self.key).unwrap()
+    }
+}
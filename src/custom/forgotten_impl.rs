@@ -0,0 +1,1188 @@
+use std::{
+    cell::RefCell,
+    collections::{hash_map::RandomState, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::{Debug, Display},
+    hash::BuildHasher,
+    marker::PhantomData,
+    ops::{DerefMut, RangeInclusive},
+    rc::{Rc, Weak},
+    sync::{self, Arc},
+};
+
+use num::{traits::ops::overflowing::OverflowingAdd, One, Zero};
+
+use crate::{AccessError, Caps, Meta, Quota, QuotaExceeded, Quotas, Tag, Trace, Tracer, Usage};
+
+use super::{
+    hooks::{Event, LocalHooks, SyncHooks},
+    Entry, ForgottenPtr, OccupiedEntry, VacantEntry,
+};
+
+/// Returned by [`Forgotten::forget_at`] when the key is already in use.
+/// The rejected value is handed back.
+pub struct Occupied<T>(pub T);
+
+impl<T> Occupied<T> {
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Debug for Occupied<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Occupied(..)")
+    }
+}
+
+impl<T> Display for Occupied<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("key is occupied")
+    }
+}
+
+impl<T> std::error::Error for Occupied<T> {}
+
+#[derive(Debug)]
+pub struct Forgotten<K, T: ?Sized, P = Rc<T>, S = RandomState> {
+    cur: K,
+    pub(super) map: HashMap<K, P, S>,
+    /// Ranges excluded from key allocation, as `start => end` (inclusive).
+    reserved: BTreeMap<K, K>,
+    /// Keys handed out by [`ForgottenRefCell::forget_ptr_with`] whose value is still being built.
+    pending: BTreeSet<K>,
+    /// Roots for [`collect`](Self::collect).
+    pinned: BTreeSet<K>,
+    epoch: u64,
+    /// Number of epochs transient entries outlive the one they were created in.
+    retention: u64,
+    /// The epochs of transient entries, which are also bucketed by epoch in `transient`.
+    epochs: BTreeMap<K, u64>,
+    transient: VecDeque<(u64, Vec<K>)>,
+    hooks: Arc<SyncHooks<K>>,
+    /// Whether hooks are called later, once [`ForgottenRefCell`] is no longer borrowed.
+    defer_hooks: bool,
+    /// Whether the [`ForgottenRefCell`] holding the store has hooks of its own,
+    /// so that events are recorded for them.
+    local_hooks: bool,
+    events: Vec<Event<K>>,
+    meta: BTreeMap<K, Meta>,
+    /// Keys of the entries with each tag.
+    tags: BTreeMap<Tag, BTreeSet<K>>,
+    quotas: Quotas<K>,
+    /// Measures values for [`Quota::with_max_bytes`].
+    size_of: fn(&T) -> usize,
+    /// Keys made by [`restrict`](Self::restrict), to the entry they alias and their capabilities.
+    aliases: BTreeMap<K, (K, Caps)>,
+    /// Aliases of each entry, removed along with it.
+    aliased: BTreeMap<K, Vec<K>>,
+    _value: PhantomData<T>,
+}
+
+impl<
+        K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One,
+        T: ?Sized,
+        P: ForgottenPtr<Target = T>,
+        S: BuildHasher,
+    > Forgotten<K, T, P, S>
+{
+    #[inline]
+    fn find_available_key(&mut self) -> Option<K> {
+        let mut k = self.cur.clone();
+        let mut wrapped = false;
+
+        loop {
+            let overflowed;
+            (k, overflowed) = k.overflowing_add(&K::one());
+            wrapped |= overflowed;
+
+            if wrapped && k >= self.cur {
+                return None;
+            }
+
+            if let Some(end) = self.reservation_end(&k) {
+                k = end.clone();
+
+                if wrapped && k >= self.cur {
+                    return None;
+                }
+
+                continue;
+            }
+
+            if !k.is_zero() && !self.is_taken(&k) {
+                self.cur = k.clone();
+                return Some(k);
+            }
+        }
+    }
+
+    /// Whether `k` holds an entry or an alias, or is being given one.
+    #[inline]
+    fn is_taken(&self, k: &K) -> bool {
+        self.map.contains_key(k) || self.aliases.contains_key(k) || self.pending.contains(k)
+    }
+
+    /// The end of the reserved range containing `k`.
+    #[inline]
+    fn reservation_end(&self, k: &K) -> Option<&K> {
+        self.reserved
+            .range(..=k)
+            .next_back()
+            .map(|(_, end)| end)
+            .filter(|end| *end >= k)
+    }
+
+    #[inline]
+    fn insert(&mut self, v: P) -> K {
+        self.insert_as(v, std::any::type_name::<T>())
+    }
+
+    /// Inserts a value whose concrete type is `type_name`, which [`on_insert`](Self::on_insert) hooks get.
+    #[inline]
+    pub(super) fn insert_as(&mut self, v: P, type_name: &'static str) -> K {
+        let k = self.find_available_key().expect("Forgotten is full");
+        self.insert_at(k.clone(), v, type_name);
+        k
+    }
+
+    #[inline]
+    pub(super) fn insert_at(&mut self, k: K, v: P, type_name: &'static str) {
+        self.emit(Event::Insert(k.clone(), type_name));
+
+        #[cfg(not(debug_assertions))]
+        self.map.insert(k, v);
+
+        #[cfg(debug_assertions)]
+        assert!(self.map.insert(k, v).is_none());
+    }
+
+    #[inline]
+    fn emit(&mut self, event: Event<K>) {
+        if self.hooks.is_empty() && !self.local_hooks {
+            return;
+        }
+
+        if self.defer_hooks {
+            self.events.push(event);
+        } else {
+            self.hooks.call(&event);
+        }
+    }
+
+    /// Calls `f` with the key of every entry inserted in the store,
+    /// and the name of the type of its value.
+    ///
+    /// That is the name of `T`, unless the concrete type is known when forgetting,
+    /// as with [`forget_any`](Self::forget_any).
+    pub fn on_insert<F: Fn(&K, &'static str) + Send + Sync + 'static>(&mut self, f: F) {
+        Arc::make_mut(&mut self.hooks).on_insert.push(Arc::new(f));
+    }
+
+    /// Calls `f` with the key of every entry removed from the store,
+    /// whether it is freed or taken.
+    pub fn on_remove<F: Fn(&K) + Send + Sync + 'static>(&mut self, f: F) {
+        Arc::make_mut(&mut self.hooks).on_remove.push(Arc::new(f));
+    }
+
+    /// Calls `f` with the key of every freed entry, after the store dropped its value.
+    pub fn on_value_dropped<F: Fn(&K) + Send + Sync + 'static>(&mut self, f: F) {
+        Arc::make_mut(&mut self.hooks)
+            .on_value_dropped
+            .push(Arc::new(f));
+    }
+
+    pub fn new() -> Self
+    where
+        S: Default,
+    {
+        Self::with_hasher(S::default())
+    }
+
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            cur: K::zero(),
+            map: HashMap::with_hasher(hash_builder),
+            reserved: BTreeMap::new(),
+            pending: BTreeSet::new(),
+            pinned: BTreeSet::new(),
+            epoch: 0,
+            retention: 0,
+            epochs: BTreeMap::new(),
+            transient: VecDeque::new(),
+            hooks: Arc::default(),
+            defer_hooks: false,
+            local_hooks: false,
+            events: Vec::new(),
+            meta: BTreeMap::new(),
+            tags: BTreeMap::new(),
+            quotas: Quotas::default(),
+            size_of: std::mem::size_of_val::<T>,
+            aliases: BTreeMap::new(),
+            aliased: BTreeMap::new(),
+            _value: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn forget(&mut self, v: T) -> K
+    where
+        T: Sized,
+    {
+        self.forget_ptr(P::new(v))
+    }
+
+    #[inline]
+    pub fn forget_and_get(&mut self, v: T) -> (K, P)
+    where
+        T: Sized,
+        P: Clone,
+    {
+        let v = P::new(v);
+        let ret = v.clone();
+        (self.forget_ptr(v), ret)
+    }
+
+    #[inline]
+    pub fn forget_ptr(&mut self, v: P) -> K {
+        self.insert(v)
+    }
+
+    /// Forgets a value built from its own key.
+    #[inline]
+    pub fn forget_with<F: FnOnce(&K) -> T>(&mut self, f: F) -> K
+    where
+        T: Sized,
+    {
+        self.forget_ptr_with(|k| P::new(f(k)))
+    }
+
+    #[inline]
+    pub fn forget_ptr_with<F: FnOnce(&K) -> P>(&mut self, f: F) -> K {
+        let k = self.find_available_key().expect("Forgotten is full");
+        let v = f(&k);
+        self.insert_at(k.clone(), v, std::any::type_name::<T>());
+        k
+    }
+
+    #[inline]
+    pub fn forget_at(&mut self, k: K, v: T) -> Result<(), Occupied<T>>
+    where
+        T: Sized,
+    {
+        if self.is_taken(&k) {
+            return Err(Occupied(v));
+        }
+
+        self.insert_at(k, P::new(v), std::any::type_name::<T>());
+        Ok(())
+    }
+
+    #[inline]
+    pub fn forget_ptr_at(&mut self, k: K, v: P) -> Result<(), Occupied<P>> {
+        if self.is_taken(&k) {
+            return Err(Occupied(v));
+        }
+
+        self.insert_at(k, v, std::any::type_name::<T>());
+        Ok(())
+    }
+
+    /// Excludes keys in `range` from being picked by [`forget`](Self::forget) and the like.
+    /// They can still be used with [`forget_at`](Self::forget_at).
+    ///
+    /// Returns `false` if `range` is empty or overlaps an existing reservation.
+    pub fn reserve(&mut self, range: RangeInclusive<K>) -> bool {
+        let (start, end) = range.into_inner();
+
+        if start > end {
+            return false;
+        }
+
+        if let Some((_, prev_end)) = self.reserved.range(..=&end).next_back() {
+            if *prev_end >= start {
+                return false;
+            }
+        }
+
+        self.reserved.insert(start, end);
+        true
+    }
+
+    /// Removes a reservation previously made with [`reserve`](Self::reserve).
+    pub fn release_reservation(&mut self, range: &RangeInclusive<K>) -> bool {
+        match self.reserved.get(range.start()) {
+            Some(end) if end == range.end() => {
+                self.reserved.remove(range.start());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    #[inline]
+    pub fn is_reserved(&self, k: &K) -> bool {
+        self.reservation_end(k).is_some()
+    }
+
+    #[inline]
+    pub fn try_free(&mut self, k: &K) -> bool {
+        self.free_checked(k).is_ok()
+    }
+
+    /// Like [`try_free`](Self::try_free), failing if `k` lacks [`Caps::FREE`]
+    /// or its entry does not exist.
+    #[inline]
+    pub fn free_checked(&mut self, k: &K) -> Result<(), AccessError> {
+        let (k, v) = self.remove_checked(k, Caps::FREE)?;
+        drop(v);
+        self.emit(Event::ValueDropped(k));
+        Ok(())
+    }
+
+    #[inline]
+    pub fn try_get(&self, k: &K) -> Option<P>
+    where
+        P: Clone,
+    {
+        self.try_ref(k).cloned()
+    }
+
+    #[inline]
+    pub fn try_ref(&self, k: &K) -> Option<&P> {
+        let k = self.access(k, Caps::READ).ok()?;
+        self.map.get(&k)
+    }
+
+    /// Like [`try_get`](Self::try_get), failing if `k` lacks [`Caps::READ`]
+    /// or its entry does not exist.
+    #[inline]
+    pub fn get_checked(&self, k: &K) -> Result<P, AccessError>
+    where
+        P: Clone,
+    {
+        let k = self.access(k, Caps::READ)?;
+        self.map.get(&k).cloned().ok_or(AccessError::NotFound)
+    }
+
+    #[inline]
+    pub fn try_mut(&mut self, k: &K) -> Option<&mut T>
+    where
+        P: DerefMut,
+    {
+        self.mut_checked(k).ok()
+    }
+
+    /// Like [`try_mut`](Self::try_mut), failing if `k` lacks [`Caps::WRITE`]
+    /// or its entry does not exist.
+    #[inline]
+    pub fn mut_checked(&mut self, k: &K) -> Result<&mut T, AccessError>
+    where
+        P: DerefMut,
+    {
+        let k = self.access(k, Caps::WRITE)?;
+
+        match self.map.get_mut(&k) {
+            Some(v) => Ok(&mut **v),
+            None => Err(AccessError::NotFound),
+        }
+    }
+
+    #[inline]
+    pub fn try_take(&mut self, k: &K) -> Option<P> {
+        self.take_checked(k).ok()
+    }
+
+    /// Like [`try_take`](Self::try_take), failing if `k` lacks [`Caps::TAKE`]
+    /// or its entry does not exist.
+    #[inline]
+    pub fn take_checked(&mut self, k: &K) -> Result<P, AccessError> {
+        self.remove_checked(k, Caps::TAKE).map(|(_, v)| v)
+    }
+
+    /// Removes the entry `k` is or aliases, if `k` has the `required` capabilities.
+    #[inline]
+    pub(super) fn remove_checked(&mut self, k: &K, required: Caps) -> Result<(K, P), AccessError> {
+        let k = self.access(k, required)?;
+        let v = self.remove(&k).ok_or(AccessError::NotFound)?;
+        Ok((k, v))
+    }
+
+    #[inline]
+    pub(super) fn remove(&mut self, k: &K) -> Option<P> {
+        self.pinned.remove(k);
+        self.epochs.remove(k);
+        self.unindex(k);
+        self.quotas.refund(k);
+
+        for alias in self.aliased.remove(k).into_iter().flatten() {
+            self.aliases.remove(&alias);
+        }
+
+        let v = self.map.remove(k)?;
+        self.emit(Event::Remove(k.clone()));
+        Some(v)
+    }
+
+    /// The capabilities of `k`, if its entry exists.
+    #[inline]
+    pub fn caps(&self, k: &K) -> Option<Caps> {
+        match self.aliases.get(k) {
+            Some((target, caps)) if self.map.contains_key(target) => Some(*caps),
+            Some(_) => None,
+            None if self.map.contains_key(k) => Some(Caps::ALL),
+            None => None,
+        }
+    }
+
+    /// Resolves `k` to the key of its entry, if it exists and `k` has the `required` capabilities.
+    #[inline]
+    fn access(&self, k: &K, required: Caps) -> Result<K, AccessError> {
+        self.caps(k).ok_or(AccessError::NotFound)?.check(required)?;
+        Ok(self.resolve(k))
+    }
+
+    /// The value of the entry `k` is or aliases, whatever the capabilities of `k`.
+    #[inline]
+    pub(super) fn peek(&self, k: &K) -> Option<&P> {
+        self.map.get(&self.resolve(k))
+    }
+
+    /// The key of the entry `k` aliases, or `k` itself.
+    #[inline]
+    fn resolve(&self, k: &K) -> K {
+        match self.aliases.get(k) {
+            Some((target, _)) => target.clone(),
+            None => k.clone(),
+        }
+    }
+
+    /// Makes a key of the same entry, with the capabilities of `k` which are in `caps`.
+    ///
+    /// Requires [`Caps::DERIVE`]. The new key is an alias,
+    /// which lives as long as the entry does, or until [`unrestrict`](Self::unrestrict).
+    pub fn restrict(&mut self, k: &K, caps: Caps) -> Result<K, AccessError> {
+        let target = self.access(k, Caps::DERIVE)?;
+        let caps = match self.aliases.get(k) {
+            Some((_, restricted)) => caps & *restricted,
+            None => caps,
+        };
+
+        let alias = self.find_available_key().expect("Forgotten is full");
+        self.aliases.insert(alias.clone(), (target.clone(), caps));
+        self.aliased.entry(target).or_default().push(alias.clone());
+        Ok(alias)
+    }
+
+    /// Drops an alias made by [`restrict`](Self::restrict), so that its key can be reused.
+    /// Returns `false` if `alias` is not one.
+    pub fn unrestrict(&mut self, alias: &K) -> bool {
+        let (target, _) = match self.aliases.remove(alias) {
+            Some(aliased) => aliased,
+            None => return false,
+        };
+
+        if let Some(aliases) = self.aliased.get_mut(&target) {
+            aliases.retain(|a| a != alias);
+
+            if aliases.is_empty() {
+                self.aliased.remove(&target);
+            }
+        }
+
+        true
+    }
+
+    /// Like [`forget`](Self::forget), with metadata to find the entry again with
+    /// [`keys_with_tag`](Self::keys_with_tag) or free it with [`free_by_tag`](Self::free_by_tag).
+    #[inline]
+    pub fn forget_with_meta(&mut self, v: T, meta: Meta) -> K
+    where
+        T: Sized,
+    {
+        self.forget_ptr_with_meta(P::new(v), meta)
+    }
+
+    #[inline]
+    pub fn forget_ptr_with_meta(&mut self, v: P, meta: Meta) -> K {
+        self.try_forget_ptr_with_meta(v, meta)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [`forget_with_meta`](Self::forget_with_meta),
+    /// failing if the value does not fit in the quota of its owner.
+    #[inline]
+    pub fn try_forget_with_meta(&mut self, v: T, meta: Meta) -> Result<K, QuotaExceeded>
+    where
+        T: Sized,
+    {
+        self.try_forget_ptr_with_meta(P::new(v), meta)
+    }
+
+    #[inline]
+    pub fn try_forget_ptr_with_meta(&mut self, v: P, meta: Meta) -> Result<K, QuotaExceeded> {
+        self.try_insert_with_meta(v, meta).map_err(|(e, _)| e)
+    }
+
+    /// Hands the value back if it does not fit in the quota of its owner,
+    /// so that it can be dropped outside [`ForgottenRefCell`].
+    pub(super) fn try_insert_with_meta(
+        &mut self,
+        v: P,
+        meta: Meta,
+    ) -> Result<K, (QuotaExceeded, P)> {
+        let bytes = (self.size_of)(&*v);
+
+        if let Some(owner) = meta.owner() {
+            if let Err(e) = self.quotas.check(owner, bytes) {
+                return Err((e, v));
+            }
+        }
+
+        let k = self.insert(v);
+
+        if let Some(owner) = meta.owner() {
+            self.quotas.charge(k.clone(), owner, bytes);
+        }
+
+        for tag in meta.tags() {
+            self.tags.entry(tag.clone()).or_default().insert(k.clone());
+        }
+
+        self.meta.insert(k.clone(), meta.stamp());
+        Ok(k)
+    }
+
+    /// Replaces the value of an entry, charging its owner with the size of the new one.
+    /// Hands the new value back if it does not fit in the quota of the owner.
+    pub(super) fn replace(&mut self, k: &K, v: P) -> Result<P, (QuotaExceeded, P)> {
+        if let Err(e) = self.quotas.recharge(k, (self.size_of)(&*v)) {
+            return Err((e, v));
+        }
+
+        Ok(std::mem::replace(self.map.get_mut(k).unwrap(), v))
+    }
+
+    /// Limits the entries forgotten with [`forget_with_meta`](Self::forget_with_meta) for `owner`.
+    /// Entries already forgotten are kept, even if they exceed the quota.
+    #[inline]
+    pub fn set_quota(&mut self, owner: u64, quota: Quota) {
+        self.quotas.set(owner, Some(quota));
+    }
+
+    #[inline]
+    pub fn remove_quota(&mut self, owner: u64) {
+        self.quotas.set(owner, None);
+    }
+
+    /// The entries of `owner`, whether it has a quota or not.
+    #[inline]
+    pub fn usage(&self, owner: u64) -> Usage {
+        self.quotas.usage(owner)
+    }
+
+    /// Sets how values are measured for [`Quota::with_max_bytes`].
+    /// By default, it is [`std::mem::size_of_val`].
+    #[inline]
+    pub fn set_size_of(&mut self, size_of: fn(&T) -> usize) {
+        self.size_of = size_of;
+    }
+
+    #[inline]
+    fn unindex(&mut self, k: &K) {
+        let meta = match self.meta.remove(k) {
+            Some(meta) => meta,
+            None => return,
+        };
+
+        for tag in meta.tags() {
+            if let Some(keys) = self.tags.get_mut(tag) {
+                keys.remove(k);
+
+                if keys.is_empty() {
+                    self.tags.remove(tag);
+                }
+            }
+        }
+    }
+
+    /// Requires [`Caps::READ`].
+    #[inline]
+    pub fn meta(&self, k: &K) -> Result<Option<&Meta>, AccessError> {
+        let k = self.access(k, Caps::READ)?;
+        Ok(self.meta.get(&k))
+    }
+
+    /// The keys of the entries tagged with `tag`, in increasing order.
+    #[inline]
+    pub fn keys_with_tag(&self, tag: &Tag) -> Vec<K> {
+        match self.tags.get(tag) {
+            Some(keys) => keys.iter().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Frees the entries tagged with `tag`, returning their keys.
+    pub fn free_by_tag(&mut self, tag: &Tag) -> Vec<K> {
+        let removed = self.remove_by_tag(tag);
+        self.drop_removed(removed)
+    }
+
+    pub(super) fn remove_by_tag(&mut self, tag: &Tag) -> Vec<(K, P)> {
+        let mut removed = Vec::new();
+
+        for k in self.keys_with_tag(tag) {
+            // the index is only trusted if the entry still has the tag
+            let tagged = matches!(self.meta.get(&k), Some(meta) if meta.tags().contains(tag));
+
+            if tagged {
+                if let Some(v) = self.remove(&k) {
+                    removed.push((k, v));
+                }
+            }
+        }
+
+        removed
+    }
+
+    /// Drops the values of removed entries, returning their keys.
+    #[inline]
+    fn drop_removed(&mut self, removed: Vec<(K, P)>) -> Vec<K> {
+        removed
+            .into_iter()
+            .map(|(k, v)| {
+                drop(v);
+                self.emit(Event::ValueDropped(k.clone()));
+                k
+            })
+            .collect()
+    }
+
+    /// Forgets a value until [`advance_epoch`](Self::advance_epoch)
+    /// has been called enough times.
+    #[inline]
+    pub fn forget_transient(&mut self, v: T) -> K
+    where
+        T: Sized,
+    {
+        self.forget_ptr_transient(P::new(v))
+    }
+
+    pub fn forget_ptr_transient(&mut self, v: P) -> K {
+        let k = self.insert(v);
+        self.epochs.insert(k.clone(), self.epoch);
+
+        match self.transient.back_mut() {
+            Some((epoch, keys)) if *epoch == self.epoch => keys.push(k.clone()),
+            _ => self.transient.push_back((self.epoch, vec![k.clone()])),
+        }
+
+        k
+    }
+
+    /// Starts a new epoch, freeing the transient entries which have expired.
+    /// Returns the number of entries freed.
+    ///
+    /// Only the expired entries are visited.
+    pub fn advance_epoch(&mut self) -> usize {
+        let removed = self.remove_expired();
+        self.drop_removed(removed).len()
+    }
+
+    pub(super) fn remove_expired(&mut self) -> Vec<(K, P)> {
+        self.epoch += 1;
+
+        let mut removed = Vec::new();
+
+        while let Some((epoch, _)) = self.transient.front() {
+            if epoch.saturating_add(self.retention) >= self.epoch {
+                break;
+            }
+
+            let (epoch, keys) = self.transient.pop_front().unwrap();
+
+            for k in keys {
+                // the entry may have been removed, and its key reused,
+                // in which case it is not in `epochs` with this epoch any more
+                if self.epochs.get(&k) == Some(&epoch) {
+                    removed.extend(self.remove(&k).map(|v| (k, v)));
+                }
+            }
+        }
+
+        removed
+    }
+
+    #[inline]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Sets how many epochs transient entries outlive the one they were created in.
+    ///
+    /// With the default of `0`, they are freed by the first [`advance_epoch`](Self::advance_epoch).
+    #[inline]
+    pub fn set_epoch_retention(&mut self, epochs: u64) {
+        self.retention = epochs;
+    }
+
+    /// Makes the entry a root for [`collect`](Self::collect). Requires [`Caps::FREE`].
+    #[inline]
+    pub fn pin(&mut self, k: &K) -> Result<(), AccessError> {
+        let k = self.access(k, Caps::FREE)?;
+        self.pinned.insert(k);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn unpin(&mut self, k: &K) -> Result<(), AccessError> {
+        let k = self.access(k, Caps::FREE)?;
+        self.pinned.remove(&k);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_pinned(&self, k: &K) -> bool {
+        self.pinned.contains(&self.resolve(k))
+    }
+
+    /// Frees the entries which can not be reached from the pinned ones
+    /// through the keys reported by [`Trace`], returning their keys.
+    pub fn collect(&mut self) -> Vec<K>
+    where
+        T: Trace<K>,
+    {
+        let removed = self.collect_removed();
+        self.drop_removed(removed)
+    }
+
+    /// Removes the entries freed by [`collect`](Self::collect), in increasing key order.
+    pub(super) fn collect_removed(&mut self) -> Vec<(K, P)>
+    where
+        T: Trace<K>,
+    {
+        let map = &self.map;
+        self.pinned.retain(|k| map.contains_key(k));
+
+        let mut marked = HashSet::new();
+        let mut pending: Vec<K> = self.pinned.iter().cloned().collect();
+        let mut tracer = Tracer::new();
+
+        while let Some(k) = pending.pop() {
+            if let Some(v) = self.map.get(&k) {
+                if !marked.contains(&k) {
+                    v.trace(&mut tracer);
+                    // values may hold aliases, which keep their entry alive
+                    pending.extend(tracer.take_keys().iter().map(|k| self.resolve(k)));
+                    marked.insert(k);
+                }
+            }
+        }
+
+        let mut freed: Vec<K> = self
+            .map
+            .keys()
+            .filter(|k| !marked.contains(*k))
+            .cloned()
+            .collect();
+        freed.sort_unstable();
+
+        freed
+            .into_iter()
+            .filter_map(|k| {
+                let v = self.remove(&k)?;
+                Some((k, v))
+            })
+            .collect()
+    }
+
+    /// An alias made by [`restrict`](Self::restrict) gives the entry it aliases,
+    /// if it has [`Caps::READ`], [`Caps::WRITE`], [`Caps::TAKE`] and [`Caps::FREE`].
+    ///
+    /// Returns `None` for zero, which can not hold an entry, for other aliases,
+    /// and for keys whose value is being built by [`ForgottenRefCell::forget_with`].
+    #[inline]
+    pub fn entry(&mut self, k: &K) -> Option<Entry<'_, K, T, P, S>> {
+        if k.is_zero() || self.pending.contains(k) {
+            return None;
+        }
+
+        let key = if self.aliases.contains_key(k) {
+            self.access(k, Caps::READ | Caps::WRITE | Caps::TAKE | Caps::FREE)
+                .ok()?
+        } else {
+            k.clone()
+        };
+
+        if self.map.contains_key(&key) {
+            Some(Entry::Occupied(OccupiedEntry { fg: self, key }))
+        } else {
+            Some(Entry::Vacant(VacantEntry { fg: self, key }))
+        }
+    }
+}
+
+impl<K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One, T: ?Sized, S: BuildHasher>
+    Forgotten<K, T, Rc<T>, S>
+{
+    #[inline]
+    pub fn forget_rc(&mut self, v: Rc<T>) -> K {
+        self.forget_ptr(v)
+    }
+
+    /// Like [`forget_with`](Self::forget_with), but also gives `f` a [`Weak`]
+    /// to the value being built, as [`Rc::new_cyclic`] does.
+    #[inline]
+    pub fn forget_cyclic<F: FnOnce(&K, &Weak<T>) -> T>(&mut self, f: F) -> K
+    where
+        T: Sized,
+    {
+        self.forget_ptr_with(|k| Rc::new_cyclic(|weak| f(k, weak)))
+    }
+}
+
+impl<K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One, T: ?Sized, S: BuildHasher>
+    Forgotten<K, T, Arc<T>, S>
+{
+    #[inline]
+    pub fn forget_arc(&mut self, v: Arc<T>) -> K {
+        self.forget_ptr(v)
+    }
+
+    /// Like [`forget_with`](Self::forget_with), but also gives `f` a [`sync::Weak`]
+    /// to the value being built, as [`Arc::new_cyclic`] does.
+    #[inline]
+    pub fn forget_cyclic<F: FnOnce(&K, &sync::Weak<T>) -> T>(&mut self, f: F) -> K
+    where
+        T: Sized,
+    {
+        self.forget_ptr_with(|k| Arc::new_cyclic(|weak| f(k, weak)))
+    }
+}
+
+impl<
+        K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One,
+        T: ?Sized,
+        P: ForgottenPtr<Target = T>,
+        S: BuildHasher + Default,
+    > Default for Forgotten<K, T, P, S>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Releases a key reserved by [`ForgottenRefCell::forget_ptr_with`],
+/// even if building the value panics.
+struct Reservation<'a, K: Ord, T: ?Sized, P, S>(&'a RefCell<Forgotten<K, T, P, S>>, &'a K);
+
+impl<K: Ord, T: ?Sized, P, S> Drop for Reservation<'_, K, T, P, S> {
+    fn drop(&mut self) {
+        self.0.borrow_mut().pending.remove(self.1);
+    }
+}
+
+pub struct ForgottenRefCell<K, T: ?Sized, P = Rc<T>, S = RandomState>(
+    pub(super) std::cell::RefCell<Forgotten<K, T, P, S>>,
+    RefCell<Arc<LocalHooks<K>>>,
+);
+
+impl<
+        K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One,
+        T: ?Sized,
+        P: ForgottenPtr<Target = T>,
+        S: BuildHasher,
+    > ForgottenRefCell<K, T, P, S>
+{
+    pub fn new() -> Self
+    where
+        S: Default,
+    {
+        Self::with_hasher(S::default())
+    }
+
+    pub fn with_hasher(hash_builder: S) -> Self {
+        let mut fg = Forgotten::with_hasher(hash_builder);
+        fg.defer_hooks = true;
+        Self(RefCell::new(fg), RefCell::default())
+    }
+
+    /// Runs `f` on the store, then calls the hooks once it is no longer borrowed.
+    #[inline]
+    pub(super) fn with_mut<R>(&self, f: impl FnOnce(&mut Forgotten<K, T, P, S>) -> R) -> R {
+        let ret = f(&mut self.0.borrow_mut());
+        self.dispatch();
+        ret
+    }
+
+    fn dispatch(&self) {
+        let (hooks, events) = {
+            let mut fg = self.0.borrow_mut();
+
+            if fg.events.is_empty() {
+                return;
+            }
+
+            (Arc::clone(&fg.hooks), std::mem::take(&mut fg.events))
+        };
+        let local = Arc::clone(&self.1.borrow());
+
+        for event in &events {
+            hooks.call(event);
+            local.call(event);
+        }
+    }
+
+    /// Registers a hook of this store only, which need not be `Send` nor `Sync`.
+    #[inline]
+    fn register(&self, f: impl FnOnce(&mut LocalHooks<K>)) {
+        f(Arc::make_mut(&mut self.1.borrow_mut()));
+        self.0.borrow_mut().local_hooks = true;
+    }
+
+    /// Hooks are called once the store is no longer borrowed, so they may use it.
+    ///
+    /// See [`Forgotten::on_insert`].
+    pub fn on_insert<F: Fn(&K, &'static str) + 'static>(&self, f: F) {
+        self.register(|hooks| hooks.on_insert.push(Arc::new(f)))
+    }
+
+    pub fn on_remove<F: Fn(&K) + 'static>(&self, f: F) {
+        self.register(|hooks| hooks.on_remove.push(Arc::new(f)))
+    }
+
+    pub fn on_value_dropped<F: Fn(&K) + 'static>(&self, f: F) {
+        self.register(|hooks| hooks.on_value_dropped.push(Arc::new(f)))
+    }
+
+    #[inline]
+    pub fn forget(&self, v: T) -> K
+    where
+        T: Sized,
+    {
+        self.with_mut(|fg| fg.forget(v))
+    }
+
+    #[inline]
+    pub fn forget_and_get(&self, v: T) -> (K, P)
+    where
+        T: Sized,
+        P: Clone,
+    {
+        self.with_mut(|fg| fg.forget_and_get(v))
+    }
+
+    #[inline]
+    pub fn forget_ptr(&self, v: P) -> K {
+        self.with_mut(|fg| fg.forget_ptr(v))
+    }
+
+    /// Forgets a value built from its own key.
+    ///
+    /// `f` is called without borrowing the store, so it may forget other values.
+    #[inline]
+    pub fn forget_with<F: FnOnce(&K) -> T>(&self, f: F) -> K
+    where
+        T: Sized,
+    {
+        self.forget_ptr_with(|k| P::new(f(k)))
+    }
+
+    pub fn forget_ptr_with<F: FnOnce(&K) -> P>(&self, f: F) -> K {
+        let k = {
+            let mut fg = self.0.borrow_mut();
+            let k = fg.find_available_key().expect("Forgotten is full");
+            fg.pending.insert(k.clone());
+            k
+        };
+
+        let reservation = Reservation(&self.0, &k);
+        let v = f(&k);
+        drop(reservation);
+
+        self.with_mut(|fg| fg.insert_at(k.clone(), v, std::any::type_name::<T>()));
+        k
+    }
+
+    /// The value is dropped once the store is no longer borrowed.
+    #[inline]
+    pub fn try_free(&self, k: &K) -> bool {
+        self.free_checked(k).is_ok()
+    }
+
+    #[inline]
+    pub fn free_checked(&self, k: &K) -> Result<(), AccessError> {
+        let (k, v) = self.with_mut(|fg| fg.remove_checked(k, Caps::FREE))?;
+        drop(v);
+        self.with_mut(|fg| fg.emit(Event::ValueDropped(k)));
+        Ok(())
+    }
+
+    #[inline]
+    pub fn get_checked(&self, k: &K) -> Result<P, AccessError>
+    where
+        P: Clone,
+    {
+        self.0.borrow().get_checked(k)
+    }
+
+    #[inline]
+    pub fn take_checked(&self, k: &K) -> Result<P, AccessError> {
+        self.with_mut(|fg| fg.take_checked(k))
+    }
+
+    #[inline]
+    pub fn caps(&self, k: &K) -> Option<Caps> {
+        self.0.borrow().caps(k)
+    }
+
+    #[inline]
+    pub fn restrict(&self, k: &K, caps: Caps) -> Result<K, AccessError> {
+        self.0.borrow_mut().restrict(k, caps)
+    }
+
+    #[inline]
+    pub fn unrestrict(&self, alias: &K) -> bool {
+        self.0.borrow_mut().unrestrict(alias)
+    }
+
+    #[inline]
+    pub fn try_get(&self, k: &K) -> Option<P>
+    where
+        P: Clone,
+    {
+        self.0.borrow().try_get(k)
+    }
+
+    #[inline]
+    pub fn try_take(&self, k: &K) -> Option<P> {
+        self.with_mut(|fg| fg.try_take(k))
+    }
+
+    #[inline]
+    pub fn forget_with_meta(&self, v: T, meta: Meta) -> K
+    where
+        T: Sized,
+    {
+        self.with_mut(|fg| fg.forget_with_meta(v, meta))
+    }
+
+    #[inline]
+    pub fn try_forget_with_meta(&self, v: T, meta: Meta) -> Result<K, QuotaExceeded>
+    where
+        T: Sized,
+    {
+        let k = self.with_mut(|fg| fg.try_insert_with_meta(P::new(v), meta));
+        k.map_err(|(e, _)| e)
+    }
+
+    #[inline]
+    pub fn set_quota(&self, owner: u64, quota: Quota) {
+        self.0.borrow_mut().set_quota(owner, quota)
+    }
+
+    #[inline]
+    pub fn remove_quota(&self, owner: u64) {
+        self.0.borrow_mut().remove_quota(owner)
+    }
+
+    #[inline]
+    pub fn usage(&self, owner: u64) -> Usage {
+        self.0.borrow().usage(owner)
+    }
+
+    #[inline]
+    pub fn set_size_of(&self, size_of: fn(&T) -> usize) {
+        self.0.borrow_mut().set_size_of(size_of)
+    }
+
+    #[inline]
+    pub fn meta(&self, k: &K) -> Result<Option<Meta>, AccessError> {
+        self.0.borrow().meta(k).map(|meta| meta.cloned())
+    }
+
+    #[inline]
+    pub fn keys_with_tag(&self, tag: &Tag) -> Vec<K> {
+        self.0.borrow().keys_with_tag(tag)
+    }
+
+    /// The values are dropped once the store is no longer borrowed.
+    pub fn free_by_tag(&self, tag: &Tag) -> Vec<K> {
+        let removed = self.with_mut(|fg| fg.remove_by_tag(tag));
+        self.drop_removed(removed)
+    }
+
+    /// Drops the values of removed entries outside the borrow, returning their keys.
+    #[inline]
+    fn drop_removed(&self, removed: Vec<(K, P)>) -> Vec<K> {
+        let keys: Vec<K> = removed
+            .into_iter()
+            .map(|(k, v)| {
+                drop(v);
+                k
+            })
+            .collect();
+
+        self.with_mut(|fg| {
+            for k in &keys {
+                fg.emit(Event::ValueDropped(k.clone()));
+            }
+        });
+
+        keys
+    }
+
+    #[inline]
+    pub fn forget_transient(&self, v: T) -> K
+    where
+        T: Sized,
+    {
+        self.with_mut(|fg| fg.forget_transient(v))
+    }
+
+    /// The values are dropped once the store is no longer borrowed.
+    pub fn advance_epoch(&self) -> usize {
+        let removed = self.with_mut(|fg| fg.remove_expired());
+        self.drop_removed(removed).len()
+    }
+
+    #[inline]
+    pub fn pin(&self, k: &K) -> Result<(), AccessError> {
+        self.0.borrow_mut().pin(k)
+    }
+
+    #[inline]
+    pub fn unpin(&self, k: &K) -> Result<(), AccessError> {
+        self.0.borrow_mut().unpin(k)
+    }
+
+    /// The values are dropped once the store is no longer borrowed.
+    pub fn collect(&self) -> Vec<K>
+    where
+        T: Trace<K>,
+    {
+        let removed = self.with_mut(|fg| fg.collect_removed());
+        self.drop_removed(removed)
+    }
+}
+
+impl<K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One, T: ?Sized, S: BuildHasher>
+    ForgottenRefCell<K, T, Rc<T>, S>
+{
+    #[inline]
+    pub fn forget_rc(&self, v: Rc<T>) -> K {
+        self.forget_ptr(v)
+    }
+}
+
+impl<
+        K: Clone + Ord + std::hash::Hash + OverflowingAdd + Zero + One,
+        T: ?Sized,
+        P: ForgottenPtr<Target = T>,
+        S: BuildHasher + Default,
+    > Default for ForgottenRefCell<K, T, P, S>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
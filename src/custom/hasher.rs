@@ -0,0 +1,95 @@
+use std::hash::{BuildHasherDefault, Hasher};
+
+/// A fast hasher for integer keys.
+///
+/// The integer itself is the state, multiplied by a large odd constant on
+/// [`finish`](Hasher::finish) so that the bits a hash map looks at are spread.
+/// It provides no protection against HashDoS.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IntHasher(u64);
+
+pub type BuildIntHasher = BuildHasherDefault<IntHasher>;
+
+impl Hasher for IntHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(b);
+        }
+    }
+
+    #[inline]
+    fn write_u8(&mut self, n: u8) {
+        self.0 = n.into();
+    }
+
+    #[inline]
+    fn write_u16(&mut self, n: u16) {
+        self.0 = n.into();
+    }
+
+    #[inline]
+    fn write_u32(&mut self, n: u32) {
+        self.0 = n.into();
+    }
+
+    #[inline]
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+
+    #[inline]
+    fn write_usize(&mut self, n: usize) {
+        self.0 = n as u64;
+    }
+
+    #[inline]
+    fn write_i8(&mut self, n: i8) {
+        self.write_u8(n as u8)
+    }
+
+    #[inline]
+    fn write_i16(&mut self, n: i16) {
+        self.write_u16(n as u16)
+    }
+
+    #[inline]
+    fn write_i32(&mut self, n: i32) {
+        self.write_u32(n as u32)
+    }
+
+    #[inline]
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64)
+    }
+
+    #[inline]
+    fn write_isize(&mut self, n: isize) {
+        self.write_usize(n as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{BuildHasher, Hash, Hasher};
+
+    use super::{BuildIntHasher, IntHasher};
+
+    fn hash<T: Hash>(v: T) -> u64 {
+        BuildIntHasher::default().hash_one(v)
+    }
+
+    #[test]
+    fn test_int_hasher() {
+        assert_eq!(hash(0u8), 0);
+        assert_eq!(hash(1u8), hash(1u64));
+        assert_eq!(hash(-1i8), hash(255u8));
+        assert_ne!(hash(1u32), hash(2u32));
+        assert_eq!(hash(7usize), IntHasher(7).finish());
+    }
+}
@@ -0,0 +1,84 @@
+use std::{fmt::Debug, sync::Arc};
+
+/// Hooks of a store which may be shared between threads.
+pub(super) type SyncHooks<K> =
+    Hooks<dyn Fn(&K, &'static str) + Send + Sync, dyn Fn(&K) + Send + Sync>;
+/// Hooks of a [`ForgottenRefCell`](super::ForgottenRefCell), which stays on its thread.
+pub(super) type LocalHooks<K> = Hooks<dyn Fn(&K, &'static str), dyn Fn(&K)>;
+
+pub(super) enum Event<K> {
+    /// The key and the name of the type of the value.
+    Insert(K, &'static str),
+    Remove(K),
+    ValueDropped(K),
+}
+
+/// Hooks called with the key and type name of inserted entries, `I`,
+/// and with the key of removed ones, `R`.
+pub(super) struct Hooks<I: ?Sized, R: ?Sized> {
+    pub(super) on_insert: Vec<Arc<I>>,
+    pub(super) on_remove: Vec<Arc<R>>,
+    pub(super) on_value_dropped: Vec<Arc<R>>,
+}
+
+impl<I: ?Sized, R: ?Sized> Hooks<I, R> {
+    #[inline]
+    pub(super) fn is_empty(&self) -> bool {
+        self.on_insert.is_empty() && self.on_remove.is_empty() && self.on_value_dropped.is_empty()
+    }
+
+    #[inline]
+    pub(super) fn call<K>(&self, event: &Event<K>)
+    where
+        I: Fn(&K, &'static str),
+        R: Fn(&K),
+    {
+        match event {
+            Event::Insert(k, type_name) => self.on_insert.iter().for_each(|f| f(k, type_name)),
+            Event::Remove(k) => self.on_remove.iter().for_each(|f| f(k)),
+            Event::ValueDropped(k) => self.on_value_dropped.iter().for_each(|f| f(k)),
+        }
+    }
+}
+
+impl<I: ?Sized, R: ?Sized> Default for Hooks<I, R> {
+    fn default() -> Self {
+        Self {
+            on_insert: Vec::new(),
+            on_remove: Vec::new(),
+            on_value_dropped: Vec::new(),
+        }
+    }
+}
+
+impl<I: ?Sized, R: ?Sized> Clone for Hooks<I, R> {
+    fn clone(&self) -> Self {
+        Self {
+            on_insert: self.on_insert.clone(),
+            on_remove: self.on_remove.clone(),
+            on_value_dropped: self.on_value_dropped.clone(),
+        }
+    }
+}
+
+impl<I: ?Sized, R: ?Sized> Debug for Hooks<I, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("on_insert", &self.on_insert.len())
+            .field("on_remove", &self.on_remove.len())
+            .field("on_value_dropped", &self.on_value_dropped.len())
+            .finish()
+    }
+}
+
+impl<K: Debug> Debug for Event<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::Insert(k, type_name) => {
+                f.debug_tuple("Insert").field(k).field(type_name).finish()
+            }
+            Event::Remove(k) => f.debug_tuple("Remove").field(k).finish(),
+            Event::ValueDropped(k) => f.debug_tuple("ValueDropped").field(k).finish(),
+        }
+    }
+}
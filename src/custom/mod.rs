@@ -0,0 +1,16 @@
+mod any;
+mod entry;
+mod forgotten_impl;
+mod hasher;
+mod hooks;
+mod ptr;
+mod sharded;
+mod sync;
+
+pub use any::*;
+pub use entry::*;
+pub use forgotten_impl::*;
+pub use hasher::*;
+pub use ptr::*;
+pub use sharded::*;
+pub use sync::*;
@@ -0,0 +1,40 @@
+use std::{ops::Deref, rc::Rc, sync::Arc};
+
+/// A smart pointer which [`Forgotten`](super::Forgotten) can store values in.
+///
+/// Implemented for [`Box`], [`Rc`] and [`Arc`].
+pub trait ForgottenPtr: Deref {
+    fn new(v: Self::Target) -> Self
+    where
+        Self::Target: Sized;
+}
+
+impl<T: ?Sized> ForgottenPtr for Box<T> {
+    #[inline]
+    fn new(v: T) -> Self
+    where
+        T: Sized,
+    {
+        Box::new(v)
+    }
+}
+
+impl<T: ?Sized> ForgottenPtr for Rc<T> {
+    #[inline]
+    fn new(v: T) -> Self
+    where
+        T: Sized,
+    {
+        Rc::new(v)
+    }
+}
+
+impl<T: ?Sized> ForgottenPtr for Arc<T> {
+    #[inline]
+    fn new(v: T) -> Self
+    where
+        T: Sized,
+    {
+        Arc::new(v)
+    }
+}
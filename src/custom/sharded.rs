@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+#[derive(Debug)]
+struct Shard<T: ?Sized> {
+    cur: usize,
+    map: HashMap<usize, Arc<T>>,
+}
+
+impl<T: ?Sized> Shard<T> {
+    fn new(index: usize, step: usize) -> Self {
+        Self {
+            cur: index.wrapping_sub(step),
+            map: HashMap::new(),
+        }
+    }
+
+    /// Keys of a shard are all congruent to its index modulo the shard count.
+    /// As the shard count is a power of two, wrapping around keeps them so.
+    #[inline]
+    fn find_available_key(&mut self, step: usize) -> Option<usize> {
+        let cur = self.cur;
+        let mut k = cur;
+
+        loop {
+            (k, _) = k.overflowing_add(step);
+
+            if k == cur {
+                return None;
+            }
+
+            if k != 0 && !self.map.contains_key(&k) {
+                self.cur = k;
+                return Some(k);
+            }
+        }
+    }
+}
+
+/// A thread safe store split into several independently locked shards.
+///
+/// The lowest bits of a key select its shard,
+/// so [`try_get`](Self::try_get) only takes a read lock on one shard.
+#[derive(Debug)]
+pub struct ShardedForgotten<T: ?Sized> {
+    shards: Box<[RwLock<Shard<T>>]>,
+    next: AtomicUsize,
+}
+
+impl<T: ?Sized> ShardedForgotten<T> {
+    /// Creates a store with four shards per available CPU.
+    pub fn new() -> Self {
+        let n = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(n * 4)
+    }
+
+    /// Creates a store with `n` shards, rounded up to a power of two.
+    pub fn with_shards(n: usize) -> Self {
+        let n = n.max(1).next_power_of_two();
+
+        Self {
+            shards: (0..n).map(|i| RwLock::new(Shard::new(i, n))).collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    #[inline]
+    fn shard_of(&self, k: &usize) -> &RwLock<Shard<T>> {
+        &self.shards[k & (self.shards.len() - 1)]
+    }
+
+    #[inline]
+    pub fn forget(&self, v: T) -> usize
+    where
+        T: Sized,
+    {
+        self.forget_arc(Arc::new(v))
+    }
+
+    #[inline]
+    pub fn forget_and_get(&self, v: T) -> (usize, Arc<T>)
+    where
+        T: Sized,
+    {
+        let v = Arc::new(v);
+        let ret = Arc::clone(&v);
+        (self.forget_arc(v), ret)
+    }
+
+    #[inline]
+    pub fn forget_arc(&self, v: Arc<T>) -> usize {
+        let step = self.shards.len();
+        let i = self.next.fetch_add(1, Ordering::Relaxed) & (step - 1);
+
+        let mut shard = self.shards[i].write().unwrap();
+        let k = shard.find_available_key(step).expect("Forgotten is full");
+
+        #[cfg(not(debug_assertions))]
+        shard.map.insert(k, v);
+
+        #[cfg(debug_assertions)]
+        assert!(shard.map.insert(k, v).is_none());
+
+        k
+    }
+
+    #[inline]
+    pub fn try_free(&self, k: &usize) -> bool {
+        self.try_take(k).is_some()
+    }
+
+    #[inline]
+    pub fn try_get(&self, k: &usize) -> Option<Arc<T>> {
+        let shard = self.shard_of(k).read().unwrap();
+        shard.map.get(k).map(Arc::clone)
+    }
+
+    #[inline]
+    pub fn try_take(&self, k: &usize) -> Option<Arc<T>> {
+        let mut shard = self.shard_of(k).write().unwrap();
+        shard.map.remove(k)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap().map.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: ?Sized> Default for ShardedForgotten<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
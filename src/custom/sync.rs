@@ -0,0 +1,135 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use num::{traits::ops::overflowing::OverflowingAdd, One, Zero};
+
+use super::Forgotten;
+
+type Lazy<K, T, S> = Option<Forgotten<K, T, Arc<T>, S>>;
+
+/// A thread safe store, which can be used in a `static`.
+///
+/// The underlying map is allocated on first insertion.
+#[derive(Debug)]
+pub struct ForgottenMutex<K, T: ?Sized, S = RandomState>(Mutex<Lazy<K, T, S>>);
+
+impl<K: Clone + Ord + Hash + OverflowingAdd + Zero + One, T: ?Sized, S: BuildHasher + Default>
+    ForgottenMutex<K, T, S>
+{
+    pub const fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    #[inline]
+    pub fn forget(&self, v: T) -> K
+    where
+        T: Sized,
+    {
+        self.forget_arc(Arc::new(v))
+    }
+
+    #[inline]
+    pub fn forget_and_get(&self, v: T) -> (K, Arc<T>)
+    where
+        T: Sized,
+    {
+        let v = Arc::new(v);
+        let ret = Arc::clone(&v);
+        (self.forget_arc(v), ret)
+    }
+
+    #[inline]
+    pub fn forget_arc(&self, v: Arc<T>) -> K {
+        let mut fg = self.0.lock().unwrap();
+        fg.get_or_insert_with(Forgotten::new).forget_arc(v)
+    }
+
+    #[inline]
+    pub fn try_free(&self, k: &K) -> bool {
+        self.try_take(k).is_some()
+    }
+
+    #[inline]
+    pub fn try_get(&self, k: &K) -> Option<Arc<T>> {
+        let fg = self.0.lock().unwrap();
+        fg.as_ref().and_then(|fg| fg.try_get(k))
+    }
+
+    #[inline]
+    pub fn try_take(&self, k: &K) -> Option<Arc<T>> {
+        let mut fg = self.0.lock().unwrap();
+        fg.as_mut().and_then(|fg| fg.try_take(k))
+    }
+}
+
+impl<K: Clone + Ord + Hash + OverflowingAdd + Zero + One, T: ?Sized, S: BuildHasher + Default>
+    Default for ForgottenMutex<K, T, S>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Same as [`ForgottenMutex`], but [`try_get`](Self::try_get) only takes a read lock.
+#[derive(Debug)]
+pub struct ForgottenRwLock<K, T: ?Sized, S = RandomState>(RwLock<Lazy<K, T, S>>);
+
+impl<K: Clone + Ord + Hash + OverflowingAdd + Zero + One, T: ?Sized, S: BuildHasher + Default>
+    ForgottenRwLock<K, T, S>
+{
+    pub const fn new() -> Self {
+        Self(RwLock::new(None))
+    }
+
+    #[inline]
+    pub fn forget(&self, v: T) -> K
+    where
+        T: Sized,
+    {
+        self.forget_arc(Arc::new(v))
+    }
+
+    #[inline]
+    pub fn forget_and_get(&self, v: T) -> (K, Arc<T>)
+    where
+        T: Sized,
+    {
+        let v = Arc::new(v);
+        let ret = Arc::clone(&v);
+        (self.forget_arc(v), ret)
+    }
+
+    #[inline]
+    pub fn forget_arc(&self, v: Arc<T>) -> K {
+        let mut fg = self.0.write().unwrap();
+        fg.get_or_insert_with(Forgotten::new).forget_arc(v)
+    }
+
+    #[inline]
+    pub fn try_free(&self, k: &K) -> bool {
+        self.try_take(k).is_some()
+    }
+
+    #[inline]
+    pub fn try_get(&self, k: &K) -> Option<Arc<T>> {
+        let fg = self.0.read().unwrap();
+        fg.as_ref().and_then(|fg| fg.try_get(k))
+    }
+
+    #[inline]
+    pub fn try_take(&self, k: &K) -> Option<Arc<T>> {
+        let mut fg = self.0.write().unwrap();
+        fg.as_mut().and_then(|fg| fg.try_take(k))
+    }
+}
+
+impl<K: Clone + Ord + Hash + OverflowingAdd + Zero + One, T: ?Sized, S: BuildHasher + Default>
+    Default for ForgottenRwLock<K, T, S>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
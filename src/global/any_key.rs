@@ -0,0 +1,162 @@
+use std::{
+    any::{Any, TypeId},
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
+
+use super::{ForgottenKey, SharedForgottenKey};
+
+/// A [`ForgottenKey`] with its type erased.
+///
+/// It still owns the entry, which is freed on drop.
+/// Like [`SharedAnyForgottenKey`], it is compared and hashed by key and [`TypeId`].
+pub struct AnyForgottenKey {
+    key: ForgottenKey<dyn Any>,
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+/// A [`SharedForgottenKey`] with its type erased.
+#[derive(Debug, Clone, Copy)]
+pub struct SharedAnyForgottenKey {
+    n: usize,
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+impl AnyForgottenKey {
+    #[inline]
+    pub fn as_usize(&self) -> &usize {
+        self.key.as_usize()
+    }
+
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    #[inline]
+    pub fn is<T: ?Sized + Any>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
+    }
+
+    #[inline]
+    pub fn downcast<T: ?Sized + Any>(mut self) -> Result<ForgottenKey<T>, Self> {
+        if self.is::<T>() {
+            Ok(unsafe { ForgottenKey::new(self.key.take_usize()) })
+        } else {
+            Err(self)
+        }
+    }
+
+    #[inline]
+    pub fn into_shared(self) -> SharedAnyForgottenKey {
+        let Self {
+            key,
+            type_id,
+            type_name,
+        } = self;
+
+        SharedAnyForgottenKey {
+            n: *key.into_shared().as_usize(),
+            type_id,
+            type_name,
+        }
+    }
+}
+
+impl<T: ?Sized + Any> From<ForgottenKey<T>> for AnyForgottenKey {
+    #[inline]
+    fn from(mut k: ForgottenKey<T>) -> Self {
+        Self {
+            key: unsafe { ForgottenKey::new(k.take_usize()) },
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+}
+
+impl PartialEq for AnyForgottenKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_usize() == other.as_usize() && self.type_id == other.type_id
+    }
+}
+
+impl Eq for AnyForgottenKey {}
+
+impl Hash for AnyForgottenKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_usize().hash(state);
+        self.type_id.hash(state);
+    }
+}
+
+impl Debug for AnyForgottenKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple(format!("AnyForgottenKey<{}>", self.type_name).as_str())
+            .field(self.as_usize())
+            .finish()
+    }
+}
+
+impl SharedAnyForgottenKey {
+    #[inline]
+    pub fn as_usize(&self) -> &usize {
+        &self.n
+    }
+
+    #[inline]
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    #[inline]
+    pub fn is<T: ?Sized + Any>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
+    }
+
+    #[inline]
+    pub fn downcast<T: ?Sized + Any>(self) -> Result<SharedForgottenKey<T>, Self> {
+        if self.is::<T>() {
+            Ok(SharedForgottenKey::new(self.n))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl<T: ?Sized + Any> From<SharedForgottenKey<T>> for SharedAnyForgottenKey {
+    #[inline]
+    fn from(k: SharedForgottenKey<T>) -> Self {
+        Self {
+            n: *k.as_usize(),
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+}
+
+impl PartialEq for SharedAnyForgottenKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.n == other.n && self.type_id == other.type_id
+    }
+}
+
+impl Eq for SharedAnyForgottenKey {}
+
+impl Hash for SharedAnyForgottenKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.n.hash(state);
+        self.type_id.hash(state);
+    }
+}
@@ -0,0 +1,56 @@
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+};
+
+use super::SharedForgottenKey;
+
+type Cast<U> = Rc<dyn Fn(Rc<dyn Any>) -> Rc<U>>;
+
+thread_local! {
+    /// `(TypeId of T, TypeId of U) => Cast<U>`
+    static CASTS: RefCell<HashMap<(TypeId, TypeId), Box<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+#[inline]
+fn find_cast<U: ?Sized + Any>(from: TypeId) -> Option<Cast<U>> {
+    CASTS.with(|cell| {
+        let casts = cell.borrow();
+        let cast = casts.get(&(from, TypeId::of::<U>()))?;
+        cast.downcast_ref::<Cast<U>>().map(Rc::clone)
+    })
+}
+
+/// Lets values stored as `T` be retrieved as `U`, usually a trait object.
+pub fn register_cast<T: Any, U: ?Sized + Any>(f: fn(Rc<T>) -> Rc<U>) {
+    let cast: Cast<U> = Rc::new(move |v: Rc<dyn Any>| f(v.downcast::<T>().unwrap()));
+
+    CASTS.with(|cell| {
+        let mut casts = cell.borrow_mut();
+        casts.insert((TypeId::of::<T>(), TypeId::of::<U>()), Box::new(cast));
+    })
+}
+
+#[inline]
+pub fn is_cast_registered<T: Any, U: ?Sized + Any>() -> bool {
+    find_cast::<U>(TypeId::of::<T>()).is_some()
+}
+
+/// Gets the value of entry `k` as `U`.
+///
+/// Returns `None` if there is no such entry,
+/// or no cast from the type of its value to `U` is registered.
+#[inline]
+pub fn get_as<U: ?Sized + Any>(k: &usize) -> Option<Rc<U>> {
+    let v = super::try_get_any(*k)?;
+    let cast = find_cast::<U>((*v).type_id())?;
+    Some(cast(v))
+}
+
+/// Gets the value of a key obtained with [`SharedForgottenKey::cast`].
+#[inline]
+pub fn try_get_as<U: ?Sized + Any>(k: &SharedForgottenKey<U>) -> Option<Rc<U>> {
+    get_as(k.as_usize())
+}
@@ -0,0 +1,88 @@
+use std::{
+    any::{Any, TypeId},
+    fmt::Debug,
+    hash::Hash,
+    marker::PhantomData,
+};
+
+use super::SharedForgottenKey;
+
+/// A key sharing the ownership of an entry with its clones.
+///
+/// Cloning adds a handle to the entry and dropping releases one.
+/// The entry is freed when the last handle is released.
+/// Handles can also be managed with [`dup_raw`](super::dup_raw)
+/// and [`release_raw`](super::release_raw).
+pub struct CountedForgottenKey<T: ?Sized + Any>(usize, u64, PhantomData<T>);
+
+impl<T: ?Sized + Any> CountedForgottenKey<T> {
+    #[inline]
+    pub fn as_usize(&self) -> &usize {
+        &self.0
+    }
+
+    /// A non-owning key to the same entry.
+    ///
+    /// It can not free or take the entry, which is only freed by releasing its last handle.
+    #[inline]
+    pub fn to_shared(&self) -> SharedForgottenKey<T> {
+        SharedForgottenKey::new(self.0)
+    }
+
+    /// Consumes the key without releasing its handle,
+    /// returning the key of the entry and its generation.
+    #[inline]
+    pub fn into_raw(self) -> (usize, u64) {
+        let raw = (self.0, self.1);
+        std::mem::forget(self);
+        raw
+    }
+
+    /// # Safety
+    ///
+    /// The caller must own a handle to the entry `n` of `generation`,
+    /// which is then owned by the returned key.
+    #[inline]
+    pub unsafe fn from_raw(n: usize, generation: u64) -> Self {
+        Self(n, generation, PhantomData)
+    }
+}
+
+impl<T: ?Sized + Any> Clone for CountedForgottenKey<T> {
+    fn clone(&self) -> Self {
+        let duped = unsafe { super::dup_raw(self.0, self.1) };
+        debug_assert!(duped.is_ok());
+        Self(self.0, self.1, PhantomData)
+    }
+}
+
+impl<T: ?Sized + Any> Drop for CountedForgottenKey<T> {
+    fn drop(&mut self) {
+        let released = unsafe { super::release_raw(self.0, self.1) };
+        debug_assert!(released.is_ok());
+    }
+}
+
+impl<T: ?Sized + Any> PartialEq for CountedForgottenKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0 && self.1 == other.1
+    }
+}
+
+impl<T: ?Sized + Any> Eq for CountedForgottenKey<T> {}
+
+impl<T: ?Sized + Any> Hash for CountedForgottenKey<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+        self.1.hash(state);
+    }
+}
+
+impl<T: ?Sized + Any> Debug for CountedForgottenKey<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple(format!("CountedForgottenKey<{:?}>", TypeId::of::<T>()).as_str())
+            .field(&self.0)
+            .field(&self.1)
+            .finish()
+    }
+}
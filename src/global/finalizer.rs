@@ -0,0 +1,15 @@
+use std::{any::Any, rc::Rc};
+
+/// Runs a finalizer with the value of a removed entry, which the store was the only one to hold.
+pub(super) type Finalizer = Box<dyn FnOnce(Rc<dyn Any>)>;
+
+pub(super) fn finalizer<T: Any>(f: impl FnOnce(T) + 'static) -> Finalizer {
+    Box::new(move |v| {
+        let v = Rc::try_unwrap(v.downcast::<T>().ok().unwrap());
+        debug_assert!(v.is_ok());
+
+        if let Ok(v) = v {
+            f(v);
+        }
+    })
+}
@@ -1,14 +1,153 @@
-use std::{any::Any, cell::RefCell, collections::HashMap, rc::Rc};
-
-use super::{ForgottenKey, SharedForgottenKey};
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    rc::{Rc, Weak},
+};
+
+use crate::{AccessError, Caps, Meta, Quota, QuotaExceeded, Quotas, Tag, Trace, Tracer, Usage};
+
+use super::{
+    finalizer::{self, Finalizer},
+    hooks::{self, Event},
+    value::Value,
+    CountedForgottenKey, Forgettable, ForgottenKey, SharedAnyForgottenKey, SharedForgottenKey,
+    StableTypeTag,
+};
 
 thread_local! {
     static FORGOTTEN: RefCell<Forgotten> =RefCell::new(Forgotten::new());
 }
 
+/// An entry of the store. Data only some entries have is kept in side maps of [`Forgotten`].
+struct Slot {
+    value: Value,
+    /// Whether a [`ForgottenKey`] owns the entry.
+    owned: bool,
+    /// Number of live [`CountedForgottenKey`]s, or `0` if the entry is not counted.
+    handles: usize,
+}
+
+impl Slot {
+    #[inline]
+    fn new(value: Value) -> Self {
+        Self {
+            value,
+            owned: true,
+            handles: 0,
+        }
+    }
+}
+
+/// The value of a removed entry, along with its finalizer.
+struct RemovedValue {
+    value: Value,
+    finalizer: Option<Finalizer>,
+}
+
+impl RemovedValue {
+    /// Drops the value, or gives it to its finalizer.
+    #[inline]
+    fn finalize(self) {
+        if let (Some(f), Value::Sized(v)) = (self.finalizer, self.value) {
+            f(v);
+        }
+    }
+
+    /// Converts the value of a taken entry, without running its finalizer.
+    #[inline]
+    fn into_taken<T: ?Sized + Forgettable>(self) -> Rc<T> {
+        T::from_value(self.value).unwrap()
+    }
+}
+
+fn trace_value<T: Trace + Any>(v: &Value, tracer: &mut Tracer) {
+    if let Value::Sized(v) = v {
+        if let Some(v) = v.downcast_ref::<T>() {
+            v.trace(tracer);
+        }
+    }
+}
+
+/// Entries removed along with another one, as `(key, value)`.
+type Descendants = Vec<(usize, RemovedValue)>;
+
+type SizeOf = Rc<dyn Fn(&dyn Any) -> usize>;
+
+/// A removed entry along with its descendants.
+///
+/// It must be dropped after the store is no longer borrowed,
+/// as dropping the values may use the store again.
+struct Removed {
+    key: usize,
+    value: RemovedValue,
+    descendants: Descendants,
+}
+
+impl Removed {
+    #[inline]
+    fn into_entries(self) -> impl Iterator<Item = (usize, RemovedValue)> {
+        std::iter::once((self.key, self.value)).chain(self.descendants)
+    }
+}
+
 struct Forgotten {
     cur: usize,
-    map: HashMap<usize, Rc<dyn Any>>,
+    map: HashMap<usize, Slot>,
+    /// Keys handed out by [`forget_with`] whose value is still being built.
+    reserved: HashSet<usize>,
+    /// Last generation given to a counted entry.
+    generations: u64,
+    /// Generation of each counted entry, which distinguishes its handles from those of
+    /// entries which had the same key before.
+    counted: HashMap<usize, u64>,
+    /// [`StableTypeTag::TAG`] of the values forgotten with [`forget_tagged`].
+    type_tags: HashMap<usize, u64>,
+    /// The entry each child was attached to with [`forget_child`].
+    parents: HashMap<usize, usize>,
+    children: HashMap<usize, Vec<SharedAnyForgottenKey>>,
+    /// Entries forgotten with [`forget_traced`]. Others are never collected.
+    traces: HashMap<usize, fn(&Value, &mut Tracer)>,
+    pinned: HashSet<usize>,
+    /// Entries whose value is known to be shared outside the store,
+    /// so that [`sweep_orphans`] frees them once it is not any more.
+    owned_elsewhere: HashSet<usize>,
+    finalizers: HashMap<usize, Finalizer>,
+    meta: HashMap<usize, Meta>,
+    epoch: u64,
+    /// The epoch of each entry forgotten with [`forget_transient`].
+    epochs: HashMap<usize, u64>,
+    /// Number of epochs transient entries outlive the one they were created in.
+    retention: u64,
+    /// Keys of transient entries, bucketed by epoch in increasing order.
+    transient: VecDeque<(u64, Vec<usize>)>,
+    /// Number of inserts between automatic [`sweep_orphans`], or `0` to disable them.
+    sweep_every: usize,
+    inserts: usize,
+    /// Whether hooks are registered, so that `events` are recorded.
+    observed: bool,
+    events: Vec<Event>,
+    /// Keys of the entries with each tag.
+    tags: HashMap<Tag, BTreeSet<usize>>,
+    quotas: Quotas<usize>,
+    /// Measures values for [`Quota::with_max_bytes`], instead of [`std::mem::size_of`].
+    size_of: Option<SizeOf>,
+    /// Keys made by [`restrict`](SharedForgottenKey::restrict) or granted by
+    /// [`Sandbox`](super::Sandbox)es, to the entries they alias.
+    aliases: HashMap<usize, Alias>,
+    /// Aliases of each entry, revoked when it is removed.
+    aliased: HashMap<usize, Vec<usize>>,
+    /// Aliases granted by each sandbox.
+    grants: HashMap<u64, HashSet<usize>>,
+    sandboxes: u64,
+}
+
+struct Alias {
+    target: usize,
+    /// The sandboxes which granted the alias or the ones it was derived from.
+    /// Revoking it from any of them revokes it.
+    sandboxes: Vec<u64>,
+    caps: Caps,
 }
 
 impl Forgotten {
@@ -24,32 +163,79 @@ impl Forgotten {
                 panic!("Forgotten is full")
             }
 
-            if v != 0 && !self.map.contains_key(&v) {
+            if v != 0
+                && !self.map.contains_key(&v)
+                && !self.reserved.contains(&v)
+                && !self.aliases.contains_key(&v)
+            {
                 return v;
             }
         }
     }
 
     #[inline]
-    fn insert(&mut self, v: Rc<dyn Any>) -> usize {
+    fn insert(&mut self, v: Value, type_name: &'static str) -> usize {
         let k = self.find_available_key();
+        self.insert_at(k, v, type_name);
+        k
+    }
+
+    #[inline]
+    fn insert_at(&mut self, k: usize, v: Value, type_name: &'static str) {
+        let v = Slot::new(v);
+        self.inserts += 1;
+
+        if self.observed {
+            self.events.push(Event::Insert(k, type_name));
+        }
 
         #[cfg(not(debug_assertions))]
         self.map.insert(k, v);
 
         #[cfg(debug_assertions)]
         assert!(self.map.insert(k, v).is_none());
-
-        k
     }
 
     fn new() -> Self {
         Self {
             cur: 0,
             map: HashMap::new(),
+            reserved: HashSet::new(),
+            generations: 0,
+            counted: HashMap::new(),
+            type_tags: HashMap::new(),
+            parents: HashMap::new(),
+            children: HashMap::new(),
+            traces: HashMap::new(),
+            pinned: HashSet::new(),
+            owned_elsewhere: HashSet::new(),
+            finalizers: HashMap::new(),
+            meta: HashMap::new(),
+            epoch: 0,
+            epochs: HashMap::new(),
+            retention: 0,
+            transient: VecDeque::new(),
+            sweep_every: 0,
+            inserts: 0,
+            observed: false,
+            events: Vec::new(),
+            tags: HashMap::new(),
+            quotas: Quotas::default(),
+            size_of: None,
+            aliases: HashMap::new(),
+            aliased: HashMap::new(),
+            grants: HashMap::new(),
+            sandboxes: 0,
         }
     }
 
+    #[inline]
+    fn reserve_available_key(&mut self) -> usize {
+        let k = self.find_available_key();
+        self.reserved.insert(k);
+        k
+    }
+
     #[inline]
     fn forget<T: Any>(&mut self, v: T) -> ForgottenKey<T> {
         self.forget_rc(Rc::new(v))
@@ -63,193 +249,1408 @@ impl Forgotten {
     }
 
     #[inline]
-    fn forget_rc<T: Any>(&mut self, v: Rc<T>) -> ForgottenKey<T> {
-        let k = self.insert(v);
-        let k = unsafe { ForgottenKey::<T>::new(k) };
+    fn forget_rc<T: ?Sized + Forgettable>(&mut self, v: Rc<T>) -> ForgottenKey<T> {
+        let k = self.insert(T::into_value(v), std::any::type_name::<T>());
+        unsafe { ForgottenKey::<T>::new(k) }
+    }
+
+    #[inline]
+    fn forget_tagged<T: StableTypeTag>(&mut self, v: T) -> ForgottenKey<T> {
+        let k = self.forget(v);
+        self.type_tags.insert(*k.as_usize(), T::TAG);
 
         k
     }
 
+    fn forget_child<P: ?Sized + Any, T: Any>(
+        &mut self,
+        parent: &SharedForgottenKey<P>,
+        v: T,
+    ) -> Result<SharedForgottenKey<T>, (AccessError, T)> {
+        let parent = match self.access(*parent.as_usize(), Caps::DERIVE) {
+            Ok(parent) => parent,
+            Err(e) => return Err((e, v)),
+        };
+
+        let k = self.insert(T::into_value(Rc::new(v)), std::any::type_name::<T>());
+        let k = SharedForgottenKey::<T>::new(k);
+
+        if let Some(slot) = self.map.get_mut(k.as_usize()) {
+            slot.owned = false;
+        }
+
+        self.parents.insert(*k.as_usize(), parent);
+        self.children.entry(parent).or_default().push(k.into());
+
+        Ok(k)
+    }
+
+    #[inline]
+    fn children(&self, n: usize) -> Result<Vec<SharedAnyForgottenKey>, AccessError> {
+        let n = self.access(n, Caps::READ)?;
+        Ok(self.children.get(&n).cloned().unwrap_or_default())
+    }
+
+    /// Removes entry `n`, detaching it from its parent and removing its descendants.
+    fn remove(&mut self, n: usize) -> Option<Removed> {
+        let slot = self.map.remove(&n)?;
+
+        if let Some(parent) = self.parents.remove(&n) {
+            if let Some(children) = self.children.get_mut(&parent) {
+                children.retain(|c| *c.as_usize() != n);
+
+                if children.is_empty() {
+                    self.children.remove(&parent);
+                }
+            }
+        }
+
+        let mut pending = self.take_children(n);
+        let value = self.detach(n, slot);
+        let mut descendants = Vec::new();
+
+        while let Some(n) = pending.pop() {
+            if let Some(child) = self.map.remove(&n) {
+                self.parents.remove(&n);
+                pending.extend(self.take_children(n));
+                descendants.push((n, self.detach(n, child)));
+            }
+        }
+
+        if self.observed {
+            self.events.push(Event::Remove(n));
+            self.events
+                .extend(descendants.iter().map(|(n, _)| Event::Remove(*n)));
+        }
+
+        Some(Removed {
+            key: n,
+            value,
+            descendants,
+        })
+    }
+
     #[inline]
-    unsafe fn try_free_with_usize(&mut self, n: usize) -> bool {
-        let v = self.map.remove(&n);
+    fn take_children(&mut self, n: usize) -> Vec<usize> {
+        let children = self.children.remove(&n).unwrap_or_default();
+        children.iter().map(|c| *c.as_usize()).collect()
+    }
 
-        v.is_some()
+    /// Forgets what the side maps hold for the removed entry `n`.
+    fn detach(&mut self, n: usize, slot: Slot) -> RemovedValue {
+        self.unindex(n);
+        self.quotas.refund(&n);
+        self.unalias(n);
+        self.counted.remove(&n);
+        self.type_tags.remove(&n);
+        self.traces.remove(&n);
+        self.pinned.remove(&n);
+        self.owned_elsewhere.remove(&n);
+        self.epochs.remove(&n);
+
+        RemovedValue {
+            value: slot.value,
+            finalizer: self.finalizers.remove(&n),
+        }
     }
 
     #[inline]
-    fn free<T: Any>(&mut self, mut k: ForgottenKey<T>) {
-        #[cfg(not(debug_assertions))]
-        unsafe {
-            self.free_with_usize(k.take_usize());
+    fn forget_traced<T: Trace + Any>(&mut self, v: T) -> ForgottenKey<T> {
+        let k = self.forget(v);
+        self.traces.insert(*k.as_usize(), trace_value::<T>);
+
+        k
+    }
+
+    #[inline]
+    fn forget_transient<T: Any>(&mut self, v: T) -> SharedForgottenKey<T> {
+        let n = self.insert(T::into_value(Rc::new(v)), std::any::type_name::<T>());
+
+        if let Some(slot) = self.map.get_mut(&n) {
+            slot.owned = false;
         }
 
-        #[cfg(debug_assertions)]
-        assert!(unsafe { self.try_free_with_usize(k.take_usize()) });
+        self.epochs.insert(n, self.epoch);
+
+        match self.transient.back_mut() {
+            Some((epoch, keys)) if *epoch == self.epoch => keys.push(n),
+            _ => self.transient.push_back((self.epoch, vec![n])),
+        }
+
+        SharedForgottenKey::new(n)
+    }
+
+    /// Returns the removed entries.
+    fn advance_epoch(&mut self) -> Vec<Removed> {
+        self.epoch += 1;
+
+        let mut removed = Vec::new();
+
+        while let Some((epoch, _)) = self.transient.front() {
+            if epoch.saturating_add(self.retention) >= self.epoch {
+                break;
+            }
+
+            let (epoch, keys) = self.transient.pop_front().unwrap();
+
+            for n in keys {
+                // the entry may have been freed, and its key reused
+                if self.epochs.get(&n) == Some(&epoch) {
+                    removed.extend(self.remove(n));
+                }
+            }
+        }
+
+        removed
     }
 
     #[inline]
-    fn try_free<T: Any>(&mut self, k: &SharedForgottenKey<T>) -> bool {
-        unsafe { self.try_free_with_usize(*k.as_usize()) }
+    fn forget_with_finalizer<T: Any>(&mut self, v: T, f: Finalizer) -> ForgottenKey<T> {
+        let k = self.forget(v);
+        self.finalizers.insert(*k.as_usize(), f);
+
+        k
     }
 
     #[inline]
-    fn get<T: Any>(&self, k: &ForgottenKey<T>) -> Rc<T> {
-        let v = self.map.get(k.as_usize()).unwrap();
-        let v = Rc::clone(v);
-        let v = v.downcast::<T>().unwrap();
-        v
+    /// Forgets a value of `bytes`, handing it back if it does not fit in the quota of its owner.
+    fn try_forget_with_meta<T: Any>(
+        &mut self,
+        v: T,
+        meta: Meta,
+        bytes: usize,
+    ) -> Result<ForgottenKey<T>, (QuotaExceeded, T)> {
+        if let Some(owner) = meta.owner() {
+            if let Err(e) = self.quotas.check(owner, bytes) {
+                return Err((e, v));
+            }
+        }
+
+        let k = self.forget(v);
+        let n = *k.as_usize();
+
+        if let Some(owner) = meta.owner() {
+            self.quotas.charge(n, owner, bytes);
+        }
+
+        for tag in meta.tags() {
+            self.tags.entry(tag.clone()).or_default().insert(n);
+        }
+
+        self.meta.insert(n, meta.stamp());
+
+        Ok(k)
     }
 
     #[inline]
-    fn try_get<T: Any>(&self, k: &SharedForgottenKey<T>) -> Option<Rc<T>> {
-        let v = self.map.get(k.as_usize());
+    fn unindex(&mut self, n: usize) {
+        let meta = match self.meta.remove(&n) {
+            Some(meta) => meta,
+            None => return,
+        };
 
-        if let Some(v) = v {
-            Some(Rc::clone(v).downcast::<T>().unwrap())
-        } else {
-            None
+        for tag in meta.tags() {
+            if let Some(keys) = self.tags.get_mut(tag) {
+                keys.remove(&n);
+
+                if keys.is_empty() {
+                    self.tags.remove(tag);
+                }
+            }
         }
     }
 
     #[inline]
-    unsafe fn try_get_with_usize<T: Any>(&self, k: &usize) -> Option<Rc<T>> {
-        let v = self.map.get(k);
+    fn meta(&self, n: usize) -> Result<Option<Meta>, AccessError> {
+        let n = self.access(n, Caps::READ)?;
+        Ok(self.meta.get(&n).cloned())
+    }
 
-        if let Some(v) = v {
-            Some(Rc::clone(v).downcast::<T>().unwrap())
-        } else {
-            None
+    #[inline]
+    fn keys_with_tag(&self, tag: &Tag) -> Vec<usize> {
+        match self.tags.get(tag) {
+            Some(keys) => keys.iter().copied().collect(),
+            None => Vec::new(),
         }
     }
 
     #[inline]
-    fn take<T: Any>(&mut self, mut k: ForgottenKey<T>) -> Rc<T> {
-        let v = self.map.remove(&k.take_usize()).unwrap();
-        let v = v.downcast::<T>().unwrap();
-        v
+    fn free_by_tag(&mut self, tag: &Tag) -> (Vec<usize>, Vec<Removed>) {
+        let mut keys = self.keys_with_tag(tag);
+        keys.retain(|n| matches!(self.map.get(n), Some(slot) if !slot.owned && slot.handles == 0));
+        let removed = keys.iter().filter_map(|n| self.remove(*n)).collect();
+        (keys, removed)
     }
 
     #[inline]
-    fn try_take<T: Any>(&mut self, k: &SharedForgottenKey<T>) -> Option<Rc<T>> {
-        let v = self.map.remove(k.as_usize());
-        if let Some(v) = v {
-            Some(v.downcast::<T>().unwrap())
+    fn set_owned_elsewhere(&mut self, n: usize, owned_elsewhere: bool) -> Result<(), AccessError> {
+        let n = self.access(n, Caps::FREE)?;
+
+        if owned_elsewhere {
+            self.owned_elsewhere.insert(n);
         } else {
-            None
+            self.owned_elsewhere.remove(&n);
         }
+
+        Ok(())
+    }
+
+    fn sweep_orphans(&mut self) -> (Vec<usize>, Vec<Removed>) {
+        self.inserts = 0;
+
+        let map = &self.map;
+        let mut orphans: Vec<usize> = self
+            .owned_elsewhere
+            .iter()
+            .copied()
+            .filter(|n| {
+                matches!(map.get(n), Some(slot) if !slot.owned
+                    && slot.handles == 0
+                    && slot.value.strong_count() == 1)
+            })
+            .collect();
+        orphans.sort_unstable();
+
+        let removed = orphans.iter().filter_map(|n| self.remove(*n)).collect();
+
+        (orphans, removed)
     }
 
     #[inline]
-    unsafe fn try_take_with_usize<T: Any>(&mut self, k: &usize) -> Option<Rc<T>> {
-        let v = self.map.remove(k);
-        if let Some(v) = v {
-            Some(v.downcast::<T>().unwrap())
+    fn auto_sweep(&mut self) -> Vec<Removed> {
+        if self.sweep_every != 0 && self.inserts >= self.sweep_every {
+            self.sweep_orphans().1
         } else {
-            None
+            Vec::new()
         }
     }
-}
 
-#[inline]
-pub fn forget<T: Any>(v: T) -> ForgottenKey<T> {
-    FORGOTTEN.with(|cell| {
-        let mut fg = cell.borrow_mut();
-        fg.forget(v)
-    })
-}
+    #[inline]
+    fn set_pinned(&mut self, n: usize, pinned: bool) -> Result<(), AccessError> {
+        let n = self.access(n, Caps::FREE)?;
 
-#[inline]
-pub fn forget_and_get<T: Any>(v: T) -> (ForgottenKey<T>, Rc<T>) {
-    FORGOTTEN.with(|cell| {
-        let mut fg = cell.borrow_mut();
-        fg.forget_and_get(v)
-    })
-}
+        if pinned {
+            self.pinned.insert(n);
+        } else {
+            self.pinned.remove(&n);
+        }
 
-#[inline]
-pub fn forget_rc<T: Any>(v: Rc<T>) -> ForgottenKey<T> {
-    FORGOTTEN.with(|cell| {
-        let mut fg = cell.borrow_mut();
-        fg.forget_rc(v)
-    })
-}
+        Ok(())
+    }
 
-#[inline]
-pub unsafe fn try_free_with_usize(n: usize) -> bool {
-    FORGOTTEN.with(|cell| {
-        let mut fg = cell.borrow_mut();
-        fg.try_free_with_usize(n)
-    })
-}
+    /// Frees the entries unreachable from the roots,
+    /// returning their keys and the removed entries.
+    fn collect(&mut self) -> (Vec<usize>, Vec<Removed>) {
+        let mut marked = HashSet::new();
+        let mut pending: Vec<usize> = self
+            .map
+            .iter()
+            .filter(|(n, slot)| {
+                slot.owned
+                    || slot.handles > 0
+                    || self.pinned.contains(n)
+                    || !self.traces.contains_key(n)
+            })
+            .map(|(n, _)| *n)
+            .collect();
+        let mut tracer = Tracer::new();
+
+        while let Some(n) = pending.pop() {
+            let slot = match self.map.get(&n) {
+                Some(slot) if marked.insert(n) => slot,
+                _ => continue,
+            };
 
-#[inline]
-pub fn free<T: Any>(k: ForgottenKey<T>) {
-    FORGOTTEN.with(|cell| {
-        let mut fg = cell.borrow_mut();
-        fg.free(k)
-    })
-}
+            if let Some(trace) = self.traces.get(&n) {
+                trace(&slot.value, &mut tracer);
+                // values may hold aliases, which keep their entry alive
+                pending.extend(tracer.take_keys().into_iter().map(|k| self.resolve(k)));
+            }
 
-#[inline]
-pub fn try_free<T: Any>(k: &SharedForgottenKey<T>) -> bool {
-    FORGOTTEN.with(|cell| {
-        let mut fg = cell.borrow_mut();
-        fg.try_free(k)
-    })
-}
+            let children = self.children.get(&n).into_iter().flatten();
+            pending.extend(children.map(|c| *c.as_usize()));
+        }
 
-#[inline]
-pub fn get<T: Any>(k: &ForgottenKey<T>) -> Rc<T> {
-    FORGOTTEN.with(|cell| {
-        let fg = cell.borrow();
-        fg.get(k)
-    })
-}
+        let unmarked: Vec<usize> = self
+            .map
+            .keys()
+            .copied()
+            .filter(|n| !marked.contains(n))
+            .collect();
+        let removed: Vec<Removed> = unmarked.iter().filter_map(|n| self.remove(*n)).collect();
+
+        // children of unreachable entries are removed along with them
+        let mut freed: Vec<usize> = removed
+            .iter()
+            .flat_map(|r| std::iter::once(r.key).chain(r.descendants.iter().map(|(n, _)| *n)))
+            .collect();
+        freed.sort_unstable();
+
+        (freed, removed)
+    }
 
-#[inline]
-pub fn try_get<T: Any>(k: &SharedForgottenKey<T>) -> Option<Rc<T>> {
-    FORGOTTEN.with(|cell| {
-        let fg = cell.borrow();
-        fg.try_get(k)
-    })
-}
+    #[inline]
+    fn tag_of(&self, n: usize) -> Result<Option<u64>, AccessError> {
+        let n = self.access(n, Caps::READ)?;
+        Ok(self.type_tags.get(&n).copied())
+    }
 
-#[inline]
-pub unsafe fn try_get_with_usize<T: Any>(k: &usize) -> Option<Rc<T>> {
-    FORGOTTEN.with(|cell| {
-        let fg = cell.borrow();
-        fg.try_get_with_usize::<T>(k)
-    })
-}
+    #[inline]
+    fn has_tag<T: ?Sized + StableTypeTag>(&self, tag: u64, n: usize) -> bool {
+        match self.map.get(&n) {
+            Some(slot) => {
+                tag == T::TAG && self.type_tags.get(&n) == Some(&tag) && slot.value.is::<T>()
+            }
+            None => false,
+        }
+    }
 
-#[inline]
-pub fn take<T: Any>(k: ForgottenKey<T>) -> Rc<T> {
-    FORGOTTEN.with(|cell| {
-        let mut fg = cell.borrow_mut();
-        fg.take(k)
-    })
-}
+    #[inline]
+    unsafe fn try_free_with_usize(&mut self, n: usize) -> Option<Removed> {
+        self.free_checked(n).ok()
+    }
 
-#[inline]
-pub fn try_take<T: Any>(k: &SharedForgottenKey<T>) -> Option<Rc<T>> {
-    FORGOTTEN.with(|cell| {
-        let mut fg = cell.borrow_mut();
-        fg.try_take(k)
-    })
-}
+    #[inline]
+    fn free_checked(&mut self, n: usize) -> Result<Removed, AccessError> {
+        let n = self.access(n, Caps::FREE)?;
+        self.remove_unowned(n)
+    }
 
-#[inline]
-pub unsafe fn try_take_with_usize<T: Any>(k: &usize) -> Option<Rc<T>> {
-    FORGOTTEN.with(|cell| {
-        let mut fg = cell.borrow_mut();
-        fg.try_take_with_usize::<T>(k)
-    })
-}
+    /// Removes entry `n` through a key which does not own it,
+    /// unless a [`ForgottenKey`] or [`CountedForgottenKey`]s do.
+    #[inline]
+    fn remove_unowned(&mut self, n: usize) -> Result<Removed, AccessError> {
+        match self.map.get(&n) {
+            Some(slot) if slot.owned || slot.handles > 0 => Err(AccessError::Owned),
+            Some(_) => self.remove(n).ok_or(AccessError::NotFound),
+            None => Err(AccessError::NotFound),
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use std::cell::RefCell;
+    #[inline]
+    fn count<T: ?Sized + Any>(&mut self, mut k: ForgottenKey<T>) -> CountedForgottenKey<T> {
+        let n = k.take_usize();
+        self.generations += 1;
+
+        if let Some(slot) = self.map.get_mut(&n) {
+            slot.owned = false;
+            slot.handles = 1;
+            self.counted.insert(n, self.generations);
+        }
+
+        unsafe { CountedForgottenKey::from_raw(n, self.generations) }
+    }
+
+    #[inline]
+    fn disown<T: ?Sized + Any>(&mut self, mut k: ForgottenKey<T>) -> SharedForgottenKey<T> {
+        let n = k.take_usize();
+
+        if let Some(slot) = self.map.get_mut(&n) {
+            slot.owned = false;
+        }
+
+        SharedForgottenKey::new(n)
+    }
+
+    #[inline]
+    fn try_own<T: ?Sized + Any>(
+        &mut self,
+        k: SharedForgottenKey<T>,
+    ) -> Result<ForgottenKey<T>, SharedForgottenKey<T>> {
+        match self.map.get_mut(k.as_usize()) {
+            Some(slot)
+                if !slot.owned
+                    && slot.handles == 0
+                    && !self.parents.contains_key(k.as_usize())
+                    && slot.value.is::<T>() =>
+            {
+                slot.owned = true;
+                self.epochs.remove(k.as_usize());
+                Ok(unsafe { ForgottenKey::new(*k.as_usize()) })
+            }
+            _ => Err(k),
+        }
+    }
+
+    #[inline]
+    unsafe fn dup_raw(&mut self, n: usize, generation: u64) -> Result<(), AccessError> {
+        let n = self.access(n, Caps::DERIVE)?;
+
+        match self.map.get_mut(&n) {
+            Some(slot) if slot.handles > 0 && self.counted.get(&n) == Some(&generation) => {
+                slot.handles += 1;
+                Ok(())
+            }
+            _ => Err(AccessError::NotFound),
+        }
+    }
+
+    /// Returns the removed slot when the last handle is released.
+    #[inline]
+    unsafe fn release_raw(
+        &mut self,
+        n: usize,
+        generation: u64,
+    ) -> Result<Option<Removed>, AccessError> {
+        let n = self.access(n, Caps::FREE)?;
+
+        match self.map.get_mut(&n) {
+            Some(slot) if slot.handles > 0 && self.counted.get(&n) == Some(&generation) => {
+                slot.handles -= 1;
+
+                if slot.handles == 0 {
+                    Ok(self.remove(n))
+                } else {
+                    Ok(None)
+                }
+            }
+            _ => Err(AccessError::NotFound),
+        }
+    }
+
+    #[inline]
+    fn free<T: ?Sized + Any>(&mut self, mut k: ForgottenKey<T>) -> Option<Removed> {
+        let removed = self.remove(k.take_usize());
+        debug_assert!(removed.is_some());
+        removed
+    }
+
+    #[inline]
+    fn try_free<T: ?Sized + Any>(&mut self, k: &SharedForgottenKey<T>) -> Option<Removed> {
+        unsafe { self.try_free_with_usize(*k.as_usize()) }
+    }
+
+    #[inline]
+    fn get<T: ?Sized + Forgettable>(&self, k: &ForgottenKey<T>) -> Rc<T> {
+        let slot = self.map.get(k.as_usize()).unwrap();
+        assert!(
+            !self.finalizers.contains_key(k.as_usize()),
+            "the value of an entry with a finalizer can not be shared"
+        );
+        T::from_value_ref(&slot.value).unwrap()
+    }
+
+    #[inline]
+    fn try_get<T: ?Sized + Forgettable>(&self, k: &SharedForgottenKey<T>) -> Option<Rc<T>> {
+        unsafe { self.try_get_with_usize(k.as_usize()) }
+    }
+
+    #[inline]
+    unsafe fn try_get_with_usize<T: ?Sized + Forgettable>(&self, k: &usize) -> Option<Rc<T>> {
+        self.get_checked(*k).ok()
+    }
+
+    #[inline]
+    fn get_checked<T: ?Sized + Forgettable>(&self, n: usize) -> Result<Rc<T>, AccessError> {
+        Ok(T::from_value_ref(self.value(n)?).unwrap())
+    }
+
+    #[inline]
+    fn try_get_any(&self, n: usize) -> Option<Rc<dyn Any>> {
+        match self.value(n).ok()? {
+            Value::Sized(v) => Some(Rc::clone(v)),
+            Value::Unsized(_) => None,
+        }
+    }
+
+    /// The value of the entry of `n`, to be shared.
+    ///
+    /// The values of entries with a finalizer are not shared,
+    /// so that the store can give the finalizer the only `Rc`.
+    #[inline]
+    fn value(&self, n: usize) -> Result<&Value, AccessError> {
+        let n = self.access(n, Caps::READ)?;
+
+        match self.map.get(&n) {
+            Some(_) if self.finalizers.contains_key(&n) => Err(AccessError::Finalized),
+            Some(slot) => Ok(&slot.value),
+            None => Err(AccessError::NotFound),
+        }
+    }
+
+    /// The value is converted with [`RemovedValue::into_taken`] once the store is no longer borrowed.
+    #[inline]
+    fn take<T: ?Sized + Any>(&mut self, mut k: ForgottenKey<T>) -> Removed {
+        self.remove(k.take_usize()).unwrap()
+    }
+
+    #[inline]
+    fn try_take<T: ?Sized + Any>(&mut self, k: &SharedForgottenKey<T>) -> Option<Removed> {
+        unsafe { self.try_take_with_usize(k.as_usize()) }
+    }
+
+    #[inline]
+    unsafe fn try_take_with_usize(&mut self, k: &usize) -> Option<Removed> {
+        self.take_checked(*k).ok()
+    }
+
+    #[inline]
+    fn take_checked(&mut self, n: usize) -> Result<Removed, AccessError> {
+        let n = self.access(n, Caps::TAKE)?;
+        self.remove_unowned(n)
+    }
+
+    /// The entry aliased by `n`, or `n` itself.
+    #[inline]
+    fn resolve(&self, n: usize) -> usize {
+        self.aliases.get(&n).map_or(n, |alias| alias.target)
+    }
+
+    /// The capabilities of key `n`, if its entry exists.
+    #[inline]
+    fn caps(&self, n: usize) -> Option<Caps> {
+        match self.aliases.get(&n) {
+            Some(alias) if self.map.contains_key(&alias.target) => Some(alias.caps),
+            Some(_) => None,
+            None if self.map.contains_key(&n) => Some(Caps::ALL),
+            None => None,
+        }
+    }
+
+    /// Resolves key `n` to its entry, if it exists and `n` has the `required` capabilities.
+    #[inline]
+    fn access(&self, n: usize, required: Caps) -> Result<usize, AccessError> {
+        self.caps(n).ok_or(AccessError::NotFound)?.check(required)?;
+        Ok(self.resolve(n))
+    }
+
+    /// Makes an alias of the entry of `n`, with at most the capabilities of `n`.
+    ///
+    /// The alias belongs to the sandboxes of `n` as well as `sandbox`,
+    /// so that aliases derived from a granted one are revoked along with it.
+    #[inline]
+    fn alias(&mut self, n: usize, caps: Caps, sandbox: Option<u64>) -> Result<usize, AccessError> {
+        let target = self.access(n, Caps::DERIVE)?;
+        let (caps, mut sandboxes) = match self.aliases.get(&n) {
+            Some(alias) => (caps & alias.caps, alias.sandboxes.clone()),
+            None => (caps, Vec::new()),
+        };
+        sandboxes.extend(sandbox.filter(|s| !sandboxes.contains(s)));
+
+        let k = self.find_available_key();
+
+        for sandbox in &sandboxes {
+            self.grants.entry(*sandbox).or_default().insert(k);
+        }
+
+        self.aliases.insert(
+            k,
+            Alias {
+                target,
+                sandboxes,
+                caps,
+            },
+        );
+        self.aliased.entry(target).or_default().push(k);
+
+        Ok(k)
+    }
+
+    #[inline]
+    fn new_sandbox(&mut self) -> u64 {
+        self.sandboxes += 1;
+        self.sandboxes
+    }
+
+    #[inline]
+    fn revoke(&mut self, sandbox: u64, k: usize) -> bool {
+        match self.aliases.get(&k) {
+            Some(alias) if alias.sandboxes.contains(&sandbox) => self.drop_alias(k),
+            _ => false,
+        }
+    }
+
+    /// Drops the alias `k`, whichever sandboxes it belongs to.
+    #[inline]
+    fn drop_alias(&mut self, k: usize) -> bool {
+        let alias = match self.aliases.remove(&k) {
+            Some(alias) => alias,
+            None => return false,
+        };
+
+        if let Some(aliases) = self.aliased.get_mut(&alias.target) {
+            aliases.retain(|a| *a != k);
+
+            if aliases.is_empty() {
+                self.aliased.remove(&alias.target);
+            }
+        }
+
+        self.ungrant(k, &alias);
+        true
+    }
+
+    #[inline]
+    fn revoke_all(&mut self, sandbox: u64) -> usize {
+        let grants = self.grants.remove(&sandbox).unwrap_or_default();
+
+        for k in &grants {
+            self.revoke(sandbox, *k);
+        }
+
+        grants.len()
+    }
+
+    /// Revokes the aliases of a removed entry.
+    #[inline]
+    fn unalias(&mut self, n: usize) {
+        for k in self.aliased.remove(&n).into_iter().flatten() {
+            if let Some(alias) = self.aliases.remove(&k) {
+                self.ungrant(k, &alias);
+            }
+        }
+    }
+
+    /// Forgets the revoked alias `k` in the sandboxes it belonged to.
+    #[inline]
+    fn ungrant(&mut self, k: usize, alias: &Alias) {
+        for sandbox in &alias.sandboxes {
+            if let Some(grants) = self.grants.get_mut(sandbox) {
+                grants.remove(&k);
+            }
+        }
+    }
+
+    /// Like [`try_take_with_usize`](Self::try_take_with_usize), without running the finalizer.
+    #[inline]
+    fn try_reclaim<T: Any>(&mut self, k: &SharedForgottenKey<T>) -> Option<(Rc<T>, Descendants)> {
+        let n = self.access(*k.as_usize(), Caps::TAKE).ok()?;
+        let Removed {
+            value, descendants, ..
+        } = self.remove_unowned(n).ok()?;
+        Some((value.into_taken(), descendants))
+    }
+}
+
+#[inline]
+pub fn forget<T: Any>(v: T) -> ForgottenKey<T> {
+    inserting(|fg| fg.forget(v))
+}
+
+#[inline]
+pub fn forget_and_get<T: Any>(v: T) -> (ForgottenKey<T>, Rc<T>) {
+    inserting(|fg| fg.forget_and_get(v))
+}
+
+#[inline]
+pub fn forget_rc<T: ?Sized + Forgettable>(v: Rc<T>) -> ForgottenKey<T> {
+    inserting(|fg| fg.forget_rc(v))
+}
+
+/// Forgets a string in a single allocation, without boxing an `Rc<str>`.
+#[inline]
+pub fn forget_str(v: impl Into<Rc<str>>) -> ForgottenKey<str> {
+    forget_rc(v.into())
+}
+
+/// Forgets a slice in a single allocation, without boxing an `Rc<[E]>`.
+#[inline]
+pub fn forget_slice<E: Any>(v: impl Into<Rc<[E]>>) -> ForgottenKey<[E]> {
+    forget_rc(v.into())
+}
+
+/// Releases a key reserved by [`forget_rc_with`],
+/// even if building the value panics.
+struct Reservation(usize);
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        FORGOTTEN.with(|cell| {
+            let mut fg = cell.borrow_mut();
+            fg.reserved.remove(&self.0);
+        })
+    }
+}
+
+fn forget_rc_with<T: Any>(f: impl FnOnce(SharedForgottenKey<T>) -> Rc<T>) -> ForgottenKey<T> {
+    let k = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.reserve_available_key()
+    });
+
+    let reservation = Reservation(k);
+    let v = f(SharedForgottenKey::new(k));
+    drop(reservation);
+
+    inserting(|fg| fg.insert_at(k, T::into_value(v), std::any::type_name::<T>()));
+    unsafe { ForgottenKey::new(k) }
+}
+
+/// Forgets a value built from its own key.
+///
+/// `f` is called without borrowing the store, so it may forget other values.
+#[inline]
+pub fn forget_with<T: Any>(f: impl FnOnce(SharedForgottenKey<T>) -> T) -> ForgottenKey<T> {
+    forget_rc_with(|k| Rc::new(f(k)))
+}
+
+/// Like [`forget_with`], but also gives `f` a [`Weak`] to the value being built,
+/// as [`Rc::new_cyclic`] does.
+#[inline]
+pub fn forget_cyclic<T: Any>(
+    f: impl FnOnce(SharedForgottenKey<T>, &Weak<T>) -> T,
+) -> ForgottenKey<T> {
+    forget_rc_with(|k| Rc::new_cyclic(|weak| f(k, weak)))
+}
+
+#[inline]
+pub fn forget_counted<T: Any>(v: T) -> CountedForgottenKey<T> {
+    forget(v).into_counted()
+}
+
+/// Like [`forget`], but tags the entry with [`StableTypeTag::TAG`].
+#[inline]
+pub fn forget_tagged<T: StableTypeTag>(v: T) -> ForgottenKey<T> {
+    inserting(|fg| fg.forget_tagged(v))
+}
+
+/// The tag of entry `k`, if it was forgotten with [`forget_tagged`].
+/// Requires [`Caps::READ`].
+#[inline]
+pub fn tag_of(k: &usize) -> Result<Option<u64>, AccessError> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.tag_of(*k)
+    })
+}
+
+/// Forgets `v` as a child of `parent`.
+///
+/// The child is freed along with its parent, whether the parent is freed, taken,
+/// or its key is dropped.
+///
+/// # Panics
+///
+/// Panics if `parent` is not in the store, or lacks [`Caps::DERIVE`].
+#[inline]
+pub fn forget_child<P: ?Sized + Any, T: Any>(
+    parent: &SharedForgottenKey<P>,
+    v: T,
+) -> SharedForgottenKey<T> {
+    try_forget_child(parent, v)
+        .unwrap_or_else(|e| panic!("parent of forget_child is not in Forgotten ({})", e))
+}
+
+/// Like [`forget_child`], failing if `parent` is not in the store or lacks [`Caps::DERIVE`].
+pub fn try_forget_child<P: ?Sized + Any, T: Any>(
+    parent: &SharedForgottenKey<P>,
+    v: T,
+) -> Result<SharedForgottenKey<T>, AccessError> {
+    // the value is handed back, and dropped, once the store is no longer borrowed
+    inserting(|fg| fg.forget_child(parent, v)).map_err(|(e, _)| e)
+}
+
+/// The entries attached to `k` with [`forget_child`]. Requires [`Caps::READ`].
+#[inline]
+pub fn children<T: ?Sized + Any>(
+    k: &SharedForgottenKey<T>,
+) -> Result<Vec<SharedAnyForgottenKey>, AccessError> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.children(*k.as_usize())
+    })
+}
+
+/// Like [`forget`], but the entry can be freed by [`collect`]
+/// once its key is shared and it is no longer reachable.
+#[inline]
+pub fn forget_traced<T: Trace + Any>(v: T) -> ForgottenKey<T> {
+    inserting(|fg| fg.forget_traced(v))
+}
+
+/// Makes the entry a root for [`collect`]. Requires [`Caps::FREE`].
+#[inline]
+pub fn pin<T: ?Sized + Any>(k: &SharedForgottenKey<T>) -> Result<(), AccessError> {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.set_pinned(*k.as_usize(), true)
+    })
+}
+
+#[inline]
+pub fn unpin<T: ?Sized + Any>(k: &SharedForgottenKey<T>) -> Result<(), AccessError> {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.set_pinned(*k.as_usize(), false)
+    })
+}
+
+/// Frees the entries which can not be reached from the roots, returning their keys.
+///
+/// The roots are the entries owned by a [`ForgottenKey`] or [`CountedForgottenKey`]s,
+/// the pinned ones, and the ones not forgotten with [`forget_traced`].
+/// Other entries are reached through the keys reported by [`Trace`],
+/// and through the children of reached entries.
+pub fn collect() -> Vec<usize> {
+    let (freed, removed) = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.collect()
+    });
+
+    notify_removed(removed);
+    freed
+}
+
+/// Forgets a value until [`advance_epoch`] has been called enough times.
+///
+/// See [`set_epoch_retention`].
+#[inline]
+pub fn forget_transient<T: Any>(v: T) -> SharedForgottenKey<T> {
+    inserting(|fg| fg.forget_transient(v))
+}
+
+/// Starts a new epoch, freeing the transient entries which have expired.
+/// Returns the number of entries freed, children excluded.
+///
+/// Only the expired entries are visited.
+pub fn advance_epoch() -> usize {
+    let removed = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.advance_epoch()
+    });
+
+    let len = removed.len();
+    notify_removed(removed);
+    len
+}
+
+#[inline]
+pub fn current_epoch() -> u64 {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.epoch
+    })
+}
+
+/// Sets how many epochs transient entries outlive the one they were created in.
+///
+/// With the default of `0`, they are freed by the first [`advance_epoch`].
+#[inline]
+pub fn set_epoch_retention(epochs: u64) {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.retention = epochs;
+    })
+}
+
+/// Marks the value as shared outside the store, e.g. the `Rc` returned by
+/// [`forget_and_get`] was kept, or not.
+/// Such entries are freed by [`sweep_orphans`] once the store holds the only `Rc`.
+/// Requires [`Caps::FREE`].
+#[inline]
+pub fn set_owned_elsewhere<T: ?Sized + Any>(
+    k: &SharedForgottenKey<T>,
+    owned_elsewhere: bool,
+) -> Result<(), AccessError> {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.set_owned_elsewhere(*k.as_usize(), owned_elsewhere)
+    })
+}
+
+/// Frees the entries [owned elsewhere](set_owned_elsewhere) whose value is
+/// no longer shared outside the store, returning their keys.
+///
+/// Entries owned by a [`ForgottenKey`] or [`CountedForgottenKey`]s are kept.
+pub fn sweep_orphans() -> Vec<usize> {
+    let (orphans, removed) = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.sweep_orphans()
+    });
+
+    notify_removed(removed);
+    orphans
+}
+
+/// Runs [`sweep_orphans`] every `inserts` inserts, or never if `inserts` is `0`.
+#[inline]
+pub fn set_auto_sweep(inserts: usize) {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.sweep_every = inserts;
+    })
+}
+
+/// Runs `f`, which inserts in the store, along with the automatic sweep if it is due.
+/// The hooks are then called with the events recorded by the insert and the sweep,
+/// once the store is no longer borrowed.
+#[inline]
+fn inserting<R>(f: impl FnOnce(&mut Forgotten) -> R) -> R {
+    let (ret, removed, observed) = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        let ret = f(&mut fg);
+        (ret, fg.auto_sweep(), fg.observed)
+    });
+
+    if observed || !removed.is_empty() {
+        notify_removed(removed);
+    }
+
+    ret
+}
+
+/// Drops the values of removed entries and calls the hooks,
+/// once the store is no longer borrowed.
+fn notify(dropped: impl IntoIterator<Item = (usize, RemovedValue)>) {
+    let hooks = hooks::hooks();
+
+    if let Some(hooks) = &hooks {
+        let events = FORGOTTEN.with(|cell| {
+            let mut fg = cell.borrow_mut();
+            std::mem::take(&mut fg.events)
+        });
+
+        for event in events {
+            match event {
+                Event::Insert(n, type_name) => hooks.on_insert.iter().for_each(|f| f(n, type_name)),
+                Event::Remove(n) => hooks.on_remove.iter().for_each(|f| f(n)),
+            }
+        }
+    }
+
+    for (n, value) in dropped {
+        value.finalize();
+
+        if let Some(hooks) = &hooks {
+            hooks.on_value_dropped.iter().for_each(|f| f(n));
+        }
+    }
+}
+
+#[inline]
+fn notify_removed(removed: impl IntoIterator<Item = Removed>) {
+    notify(removed.into_iter().flat_map(Removed::into_entries))
+}
+
+#[inline]
+pub(super) fn observe(observed: bool) {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.observed = observed;
+
+        if !observed {
+            fg.events.clear();
+        }
+    })
+}
+
+#[inline]
+pub(super) fn has_tag<T: ?Sized + StableTypeTag>(tag: u64, n: usize) -> bool {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.has_tag::<T>(tag, n)
+    })
+}
+
+#[inline]
+pub(super) fn new_sandbox() -> u64 {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.new_sandbox()
+    })
+}
+
+#[inline]
+pub(super) fn grant(sandbox: u64, n: usize, caps: Caps) -> Option<usize> {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.alias(n, caps, Some(sandbox)).ok()
+    })
+}
+
+#[inline]
+pub(super) fn restrict(n: usize, caps: Caps) -> Result<usize, AccessError> {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.alias(n, caps, None)
+    })
+}
+
+/// The capabilities of the key, or `None` if its entry does not exist.
+#[inline]
+pub fn caps<T: ?Sized + Any>(k: &SharedForgottenKey<T>) -> Option<Caps> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.caps(*k.as_usize())
+    })
+}
+
+#[inline]
+pub(super) fn unrestrict(k: usize) -> bool {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.drop_alias(k)
+    })
+}
+
+#[inline]
+pub(super) fn revoke(sandbox: u64, k: usize) -> bool {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.revoke(sandbox, k)
+    })
+}
+
+#[inline]
+pub(super) fn revoke_all(sandbox: u64) -> usize {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.revoke_all(sandbox)
+    })
+}
+
+#[inline]
+pub(super) fn disown<T: ?Sized + Any>(k: ForgottenKey<T>) -> SharedForgottenKey<T> {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.disown(k)
+    })
+}
+
+#[inline]
+pub(super) fn try_own<T: ?Sized + Any>(
+    k: SharedForgottenKey<T>,
+) -> Result<ForgottenKey<T>, SharedForgottenKey<T>> {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.try_own(k)
+    })
+}
+
+#[inline]
+pub(super) fn count<T: ?Sized + Any>(k: ForgottenKey<T>) -> CountedForgottenKey<T> {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.count(k)
+    })
+}
+
+/// Adds a handle to an entry counted by [`CountedForgottenKey`]s.
+///
+/// `generation` is the one returned by [`CountedForgottenKey::into_raw`].
+/// Requires [`Caps::DERIVE`], and fails if there is no such entry,
+/// such as when its key was reused by an entry of another generation.
+///
+/// # Safety
+///
+/// Every successful call must be balanced by a [`release_raw`].
+#[inline]
+pub unsafe fn dup_raw(n: usize, generation: u64) -> Result<(), AccessError> {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.dup_raw(n, generation)
+    })
+}
+
+/// Releases a handle to an entry counted by [`CountedForgottenKey`]s,
+/// freeing the entry when it was the last one.
+///
+/// Requires [`Caps::FREE`], and fails if there is no such entry, as in [`dup_raw`].
+///
+/// # Safety
+///
+/// The caller must own the handle being released.
+#[inline]
+pub unsafe fn release_raw(n: usize, generation: u64) -> Result<(), AccessError> {
+    let released = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.release_raw(n, generation)
+    });
+
+    notify_removed(released?);
+    Ok(())
+}
+
+/// Frees the entry of a [`ForgottenKey`] being dropped.
+#[inline]
+pub(super) fn free_owned(n: usize) {
+    let removed = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.remove(n)
+    });
+
+    notify_removed(removed);
+}
+
+#[inline]
+pub unsafe fn try_free_with_usize(n: usize) -> bool {
+    let removed = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.try_free_with_usize(n)
+    });
+
+    let freed = removed.is_some();
+    notify_removed(removed);
+    freed
+}
+
+#[inline]
+pub fn free<T: ?Sized + Any>(k: ForgottenKey<T>) {
+    let removed = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.free(k)
+    });
+
+    notify_removed(removed);
+}
+
+#[inline]
+pub fn try_free<T: ?Sized + Any>(k: &SharedForgottenKey<T>) -> bool {
+    let removed = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.try_free(k)
+    });
+
+    let freed = removed.is_some();
+    notify_removed(removed);
+    freed
+}
+
+/// Like [`try_free`], failing if the key lacks [`Caps::FREE`] or its entry does not exist.
+#[inline]
+pub fn free_checked<T: ?Sized + Any>(k: &SharedForgottenKey<T>) -> Result<(), AccessError> {
+    let removed = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.free_checked(*k.as_usize())
+    })?;
+
+    notify_removed(Some(removed));
+    Ok(())
+}
+
+#[inline]
+pub fn get<T: ?Sized + Forgettable>(k: &ForgottenKey<T>) -> Rc<T> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.get(k)
+    })
+}
+
+#[inline]
+pub fn try_get<T: ?Sized + Forgettable>(k: &SharedForgottenKey<T>) -> Option<Rc<T>> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.try_get(k)
+    })
+}
+
+#[inline]
+pub unsafe fn try_get_with_usize<T: ?Sized + Forgettable>(k: &usize) -> Option<Rc<T>> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.try_get_with_usize::<T>(k)
+    })
+}
+
+/// Like [`try_get`], failing if the key lacks [`Caps::READ`] or its entry does not exist.
+#[inline]
+pub fn get_checked<T: ?Sized + Forgettable>(
+    k: &SharedForgottenKey<T>,
+) -> Result<Rc<T>, AccessError> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.get_checked(*k.as_usize())
+    })
+}
+
+#[inline]
+pub(super) fn try_get_any(n: usize) -> Option<Rc<dyn Any>> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.try_get_any(n)
+    })
+}
+
+/// Forgets a value, and gives it to `f` when the entry is freed.
+///
+/// `f` runs before the call removing the entry returns, once the store is no longer borrowed.
+/// So that the store holds the only `Rc` to the value, it can not be read:
+/// [`get_checked`] fails with [`AccessError::Finalized`], and [`get`] panics.
+/// Taking the value back with [`take`] or [`reclaim`] does not run `f`.
+///
+/// Entries left in the store when the thread exits are dropped without running `f`.
+#[inline]
+pub fn forget_with_finalizer<T: Any>(v: T, f: impl FnOnce(T) + 'static) -> ForgottenKey<T> {
+    let f = finalizer::finalizer(f);
+    inserting(|fg| fg.forget_with_finalizer(v, f))
+}
+
+/// Like [`forget`], with metadata to find the entry again with [`keys_with_tag`]
+/// or free it with [`free_by_tag`].
+#[inline]
+pub fn forget_with_meta<T: Any>(v: T, meta: Meta) -> ForgottenKey<T> {
+    try_forget_with_meta(v, meta).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`forget_with_meta`], failing if the value does not fit in the quota of its owner.
+pub fn try_forget_with_meta<T: Any>(v: T, meta: Meta) -> Result<ForgottenKey<T>, QuotaExceeded> {
+    let size_of = FORGOTTEN.with(|cell| cell.borrow().size_of.clone());
+    let bytes = match size_of {
+        Some(size_of) => size_of(&v),
+        None => std::mem::size_of::<T>(),
+    };
+
+    // the value is handed back, and dropped, once the store is no longer borrowed
+    inserting(|fg| fg.try_forget_with_meta(v, meta, bytes)).map_err(|(e, _)| e)
+}
+
+/// Limits the entries forgotten with [`forget_with_meta`] for `owner`.
+/// Entries already forgotten are kept, even if they exceed the quota.
+#[inline]
+pub fn set_quota(owner: u64, quota: Quota) {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.quotas.set(owner, Some(quota))
+    })
+}
+
+#[inline]
+pub fn remove_quota(owner: u64) {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.quotas.set(owner, None)
+    })
+}
+
+/// The entries of `owner`, whether it has a quota or not.
+#[inline]
+pub fn usage(owner: u64) -> Usage {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.quotas.usage(owner)
+    })
+}
+
+/// Sets how values are measured for [`Quota::with_max_bytes`].
+/// By default, only their inline size is counted.
+#[inline]
+pub fn set_size_of<F: Fn(&dyn Any) -> usize + 'static>(f: F) {
+    FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.size_of = Some(Rc::new(f));
+    })
+}
+
+/// The metadata of the entry, if forgotten with [`forget_with_meta`].
+/// Requires [`Caps::READ`].
+#[inline]
+pub fn meta<T: ?Sized + Any>(k: &SharedForgottenKey<T>) -> Result<Option<Meta>, AccessError> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.meta(*k.as_usize())
+    })
+}
+
+/// The keys of the entries tagged with `tag`, in increasing order.
+#[inline]
+pub fn keys_with_tag(tag: &Tag) -> Vec<usize> {
+    FORGOTTEN.with(|cell| {
+        let fg = cell.borrow();
+        fg.keys_with_tag(tag)
+    })
+}
+
+/// Frees the entries tagged with `tag`, returning their keys.
+///
+/// Entries owned by a [`ForgottenKey`] or [`CountedForgottenKey`]s are kept.
+pub fn free_by_tag(tag: &Tag) -> Vec<usize> {
+    let (keys, removed) = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.free_by_tag(tag)
+    });
+
+    notify_removed(removed);
+    keys
+}
+
+/// Takes the value without running the finalizer given to [`forget_with_finalizer`].
+#[inline]
+pub fn reclaim<T: Any>(k: ForgottenKey<T>) -> Rc<T> {
+    let v = try_reclaim(&k.into_shared());
+    v.unwrap()
+}
+
+#[inline]
+pub fn try_reclaim<T: Any>(k: &SharedForgottenKey<T>) -> Option<Rc<T>> {
+    let (v, descendants) = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.try_reclaim(k)
+    })?;
+
+    notify(descendants);
+    Some(v)
+}
+
+#[inline]
+pub fn take<T: ?Sized + Forgettable>(k: ForgottenKey<T>) -> Rc<T> {
+    let Removed {
+        value, descendants, ..
+    } = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.take(k)
+    });
+
+    let v = value.into_taken();
+    notify(descendants);
+    v
+}
+
+#[inline]
+pub fn try_take<T: ?Sized + Forgettable>(k: &SharedForgottenKey<T>) -> Option<Rc<T>> {
+    let Removed {
+        value, descendants, ..
+    } = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.try_take(k)
+    })?;
+
+    let v = value.into_taken();
+    notify(descendants);
+    Some(v)
+}
+
+/// Like [`try_take`], failing if the key lacks [`Caps::TAKE`] or its entry does not exist.
+#[inline]
+pub fn take_checked<T: ?Sized + Forgettable>(
+    k: &SharedForgottenKey<T>,
+) -> Result<Rc<T>, AccessError> {
+    let Removed {
+        value, descendants, ..
+    } = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.take_checked(*k.as_usize())
+    })?;
+
+    let v = value.into_taken();
+    notify(descendants);
+    Ok(v)
+}
+
+#[inline]
+pub unsafe fn try_take_with_usize<T: ?Sized + Forgettable>(k: &usize) -> Option<Rc<T>> {
+    let Removed {
+        value, descendants, ..
+    } = FORGOTTEN.with(|cell| {
+        let mut fg = cell.borrow_mut();
+        fg.try_take_with_usize(k)
+    })?;
+
+    let v = value.into_taken();
+    notify(descendants);
+    Some(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    #[test]
+    fn slots_stay_small() {
+        use std::mem::size_of;
+
+        // data of optional features lives in side maps, leaving the value and who owns it
+        assert!(size_of::<super::Slot>() <= size_of::<super::Value>() + 2 * size_of::<usize>());
+    }
 
     #[test]
     fn test_drop_key_1() {
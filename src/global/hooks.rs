@@ -0,0 +1,59 @@
+use std::{cell::RefCell, rc::Rc};
+
+/// A change of the store, reported to the hooks once it is no longer borrowed.
+pub(super) enum Event {
+    Insert(usize, &'static str),
+    Remove(usize),
+}
+
+#[derive(Default, Clone)]
+pub(super) struct Hooks {
+    pub(super) on_insert: Vec<Rc<dyn Fn(usize, &'static str)>>,
+    pub(super) on_remove: Vec<Rc<dyn Fn(usize)>>,
+    pub(super) on_value_dropped: Vec<Rc<dyn Fn(usize)>>,
+}
+
+thread_local! {
+    /// `None` until a hook is registered.
+    static HOOKS: RefCell<Option<Rc<Hooks>>> = const { RefCell::new(None) };
+}
+
+/// The registered hooks, which can be called while registering new ones.
+#[inline]
+pub(super) fn hooks() -> Option<Rc<Hooks>> {
+    HOOKS.with(|cell| cell.borrow().clone())
+}
+
+/// Registering copies the hooks only if they are being called.
+#[inline]
+fn register(f: impl FnOnce(&mut Hooks)) {
+    HOOKS.with(|cell| {
+        let mut hooks = cell.borrow_mut();
+        f(Rc::make_mut(hooks.get_or_insert_with(Rc::default)))
+    });
+    super::observe(true);
+}
+
+/// Calls `f` with the key and type name of every entry inserted in the store.
+///
+/// Hooks are called once the store is no longer borrowed, so they may use it.
+pub fn on_insert(f: impl Fn(usize, &'static str) + 'static) {
+    register(|hooks| hooks.on_insert.push(Rc::new(f)))
+}
+
+/// Calls `f` with the key of every entry removed from the store,
+/// whether it is freed or taken.
+pub fn on_remove(f: impl Fn(usize) + 'static) {
+    register(|hooks| hooks.on_remove.push(Rc::new(f)))
+}
+
+/// Calls `f` with the key of every freed entry, after the store dropped its value.
+/// The value itself lives on if it is still shared elsewhere.
+pub fn on_value_dropped(f: impl Fn(usize) + 'static) {
+    register(|hooks| hooks.on_value_dropped.push(Rc::new(f)))
+}
+
+pub fn clear_hooks() {
+    HOOKS.with(|cell| *cell.borrow_mut() = None);
+    super::observe(false);
+}
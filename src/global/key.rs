@@ -4,11 +4,11 @@ use std::{
     marker::PhantomData,
 };
 
-use super::SharedForgottenKey;
+use super::{CountedForgottenKey, SharedForgottenKey};
 
-pub struct ForgottenKey<T: Any>(usize, PhantomData<T>);
+pub struct ForgottenKey<T: ?Sized + Any>(usize, PhantomData<T>);
 
-impl<T: Any> ForgottenKey<T> {
+impl<T: ?Sized + Any> ForgottenKey<T> {
     #[inline]
     pub(super) fn take_usize(&mut self) -> usize {
         std::mem::replace(&mut self.0, 0)
@@ -19,9 +19,25 @@ impl<T: Any> ForgottenKey<T> {
         &self.0
     }
 
+    /// A non-owning key to the same entry, e.g. for [`forget_child`](super::forget_child).
+    ///
+    /// It can not free or take the entry while this key owns it.
     #[inline]
-    pub fn into_shared(mut self) -> SharedForgottenKey<T> {
-        SharedForgottenKey::<T>::new(self.take_usize())
+    pub fn to_shared(&self) -> SharedForgottenKey<T> {
+        SharedForgottenKey::new(self.0)
+    }
+
+    /// Gives up the ownership of the entry, which is no longer freed on drop.
+    ///
+    /// See [`SharedForgottenKey::try_into_owned`] for the reverse.
+    #[inline]
+    pub fn into_shared(self) -> SharedForgottenKey<T> {
+        super::disown(self)
+    }
+
+    #[inline]
+    pub fn into_counted(self) -> CountedForgottenKey<T> {
+        super::count(self)
     }
 
     #[inline]
@@ -30,7 +46,7 @@ impl<T: Any> ForgottenKey<T> {
     }
 }
 
-impl<T: Any> Debug for ForgottenKey<T> {
+impl<T: ?Sized + Any> Debug for ForgottenKey<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple(format!("ForgottenKey<{:?}>", TypeId::of::<T>()).as_str())
             .field(&self.0)
@@ -38,10 +54,10 @@ impl<T: Any> Debug for ForgottenKey<T> {
     }
 }
 
-impl<T: Any> Drop for ForgottenKey<T> {
+impl<T: ?Sized + Any> Drop for ForgottenKey<T> {
     fn drop(&mut self) {
         if self.0 != 0 {
-            unsafe { super::try_free_with_usize(self.0) };
+            super::free_owned(self.0);
         }
     }
 }
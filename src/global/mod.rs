@@ -1,7 +1,22 @@
+mod any_key;
+mod cast;
+mod counted_key;
+mod finalizer;
 mod forgotten_impl;
+mod hooks;
 mod key;
+mod sandbox;
 mod shared_key;
+mod tag;
+mod value;
 
+pub use any_key::*;
+pub use cast::*;
+pub use counted_key::*;
 pub use forgotten_impl::*;
+pub use hooks::{clear_hooks, on_insert, on_remove, on_value_dropped};
 pub use key::*;
+pub use sandbox::*;
 pub use shared_key::*;
+pub use tag::*;
+pub use value::Forgettable;
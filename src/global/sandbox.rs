@@ -0,0 +1,68 @@
+use std::any::Any;
+
+use crate::Caps;
+
+use super::SharedForgottenKey;
+
+/// Grants aliases of entries, which can all be revoked at once,
+/// for example when a plugin they were given to is unloaded.
+///
+/// An alias is a key of its own, whose value is the one of the entry it aliases.
+/// Unless granted with other [`Caps`], it can only be used to read the value:
+/// taking or freeing it does nothing.
+/// Aliases are revoked when their entry is removed, and when the sandbox is dropped,
+/// along with the aliases derived from them.
+#[derive(Debug)]
+pub struct Sandbox(u64);
+
+impl Sandbox {
+    #[inline]
+    pub fn new() -> Self {
+        Self(super::new_sandbox())
+    }
+
+    /// Grants a read only alias of the entry `k` is or aliases,
+    /// or returns `None` if it does not exist or `k` lacks [`Caps::DERIVE`].
+    #[inline]
+    pub fn grant<T: ?Sized + Any>(
+        &self,
+        k: &SharedForgottenKey<T>,
+    ) -> Option<SharedForgottenKey<T>> {
+        self.grant_with_caps(k, Caps::READ)
+    }
+
+    /// Like [`grant`](Self::grant), with the capabilities of `k` which are in `caps`.
+    #[inline]
+    pub fn grant_with_caps<T: ?Sized + Any>(
+        &self,
+        k: &SharedForgottenKey<T>,
+        caps: Caps,
+    ) -> Option<SharedForgottenKey<T>> {
+        super::grant(self.0, *k.as_usize(), caps).map(SharedForgottenKey::new)
+    }
+
+    /// Revokes an alias granted by this sandbox.
+    #[inline]
+    pub fn revoke<T: ?Sized + Any>(&self, k: &SharedForgottenKey<T>) -> bool {
+        super::revoke(self.0, *k.as_usize())
+    }
+
+    /// Revokes every alias granted by this sandbox, returning how many there were.
+    /// The aliased entries, and aliases granted by other sandboxes, are kept.
+    #[inline]
+    pub fn revoke_all(&self) -> usize {
+        super::revoke_all(self.0)
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        self.revoke_all();
+    }
+}
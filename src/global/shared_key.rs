@@ -4,16 +4,20 @@ use std::{
     marker::PhantomData,
 };
 
+use crate::{AccessError, Caps};
+
+use super::{ForgottenKey, StableTypeTag};
+
 #[derive(Hash)]
-pub struct SharedForgottenKey<T: Any>(usize, PhantomData<T>);
+pub struct SharedForgottenKey<T: ?Sized + Any>(usize, PhantomData<T>);
 
-impl<T: Any> SharedForgottenKey<T> {
+impl<T: ?Sized + Any> SharedForgottenKey<T> {
     pub(crate) fn new(n: usize) -> Self {
         Self(n, PhantomData)
     }
 }
 
-impl<T: Any> SharedForgottenKey<T> {
+impl<T: ?Sized + Any> SharedForgottenKey<T> {
     pub fn as_usize(&self) -> &usize {
         &self.0
     }
@@ -25,25 +29,91 @@ impl<T: Any> SharedForgottenKey<T> {
     pub unsafe fn from_usize(n: usize) -> Self {
         Self(n, PhantomData)
     }
+
+    /// Like [`into_type_and_usize`](Self::into_type_and_usize),
+    /// with a tag which is stable across compilations.
+    #[inline]
+    pub fn into_tag_and_usize(self) -> (u64, usize)
+    where
+        T: StableTypeTag,
+    {
+        (T::TAG, self.0)
+    }
+
+    /// The reverse of [`into_tag_and_usize`](Self::into_tag_and_usize).
+    ///
+    /// Returns `None` unless entry `n` exists and was forgotten with
+    /// [`forget_tagged`](super::forget_tagged) as a `T` whose tag is `tag`.
+    #[inline]
+    pub fn from_tag_and_usize(tag: u64, n: usize) -> Option<Self>
+    where
+        T: StableTypeTag,
+    {
+        if super::has_tag::<T>(tag, n) {
+            Some(Self(n, PhantomData))
+        } else {
+            None
+        }
+    }
+
+    /// Retypes the key as `U`, if a cast from `T` to `U` is
+    /// [registered](super::register_cast).
+    /// Its value can then be retrieved with [`try_get_as`](super::try_get_as).
+    #[inline]
+    pub fn cast<U: ?Sized + Any>(self) -> Option<SharedForgottenKey<U>>
+    where
+        T: Sized,
+    {
+        if super::is_cast_registered::<T, U>() {
+            Some(SharedForgottenKey::new(self.0))
+        } else {
+            None
+        }
+    }
+
+    /// Makes a key of the same entry, with the capabilities of this key which are in `caps`.
+    ///
+    /// Requires [`Caps::DERIVE`]. The new key is an alias,
+    /// which lives as long as the entry does, or until [`unrestrict`](Self::unrestrict).
+    #[inline]
+    pub fn restrict(&self, caps: Caps) -> Result<Self, AccessError> {
+        super::restrict(self.0, caps).map(Self::new)
+    }
+
+    /// Drops this alias, also revoking it in the sandboxes it was granted by.
+    /// Returns `false` if this key is not an alias.
+    #[inline]
+    pub fn unrestrict(self) -> bool {
+        super::unrestrict(self.0)
+    }
+
+    /// Takes back the ownership of the entry.
+    ///
+    /// Fails if the entry does not exist, holds another type,
+    /// or is already owned by a [`ForgottenKey`] or [`CountedForgottenKey`](super::CountedForgottenKey)s.
+    #[inline]
+    pub fn try_into_owned(self) -> Result<ForgottenKey<T>, Self> {
+        super::try_own(self)
+    }
 }
 
-impl<T: Any> PartialEq for SharedForgottenKey<T> {
+impl<T: ?Sized + Any> PartialEq for SharedForgottenKey<T> {
     fn eq(&self, other: &Self) -> bool {
         self.0 == other.0
     }
 }
 
-impl<T: Any> Eq for SharedForgottenKey<T> {}
+impl<T: ?Sized + Any> Eq for SharedForgottenKey<T> {}
 
-impl<T: Any> Clone for SharedForgottenKey<T> {
+impl<T: ?Sized + Any> Clone for SharedForgottenKey<T> {
     fn clone(&self) -> Self {
         Self(self.0.clone(), PhantomData)
     }
 }
 
-impl<T: Any> Copy for SharedForgottenKey<T> {}
+impl<T: ?Sized + Any> Copy for SharedForgottenKey<T> {}
 
-impl<T: Any> Debug for SharedForgottenKey<T> {
+impl<T: ?Sized + Any> Debug for SharedForgottenKey<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple(format!("SharedForgottenKey<{:?}>", TypeId::of::<T>()).as_str())
             .field(&self.0)
@@ -0,0 +1,29 @@
+use std::any::Any;
+
+/// A tag identifying a type which, unlike [`TypeId`](std::any::TypeId),
+/// stays the same across compilations.
+///
+/// Entries forgotten with [`forget_tagged`](super::forget_tagged) carry the tag,
+/// so that raw handles can be checked with
+/// [`SharedForgottenKey::from_tag_and_usize`](super::SharedForgottenKey::from_tag_and_usize).
+///
+/// The tag is either picked by hand or derived from a name with [`type_tag`].
+/// Two types must not share a tag.
+pub trait StableTypeTag: Any {
+    const TAG: u64;
+}
+
+/// Hashes `name` (FNV-1a) into a tag for [`StableTypeTag::TAG`].
+pub const fn type_tag(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+
+    hash
+}
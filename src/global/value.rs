@@ -0,0 +1,207 @@
+use std::{
+    any::{Any, TypeId},
+    fmt::Debug,
+    mem::ManuallyDrop,
+    ptr,
+    rc::Rc,
+};
+
+/// A value in the global store.
+///
+/// Unsized values are kept in their own `Rc` allocation,
+/// rather than boxed once more to be coerced to `Rc<dyn Any>`.
+pub enum Value {
+    Sized(Rc<dyn Any>),
+    Unsized(UnsizedRc),
+}
+
+impl Value {
+    #[inline]
+    pub(super) fn type_id(&self) -> TypeId {
+        match self {
+            Value::Sized(v) => (**v).type_id(),
+            Value::Unsized(v) => v.type_id,
+        }
+    }
+
+    #[inline]
+    pub(super) fn strong_count(&self) -> usize {
+        match self {
+            Value::Sized(v) => Rc::strong_count(v),
+            Value::Unsized(v) => unsafe { (v.strong_count)(v.ptr, v.len) },
+        }
+    }
+
+    #[inline]
+    pub(super) fn is<T: ?Sized + Any>(&self) -> bool {
+        self.type_id() == TypeId::of::<T>()
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Value").field(&self.type_id()).finish()
+    }
+}
+
+/// A type erased `Rc<[E]>`, or any unsized type with the same layout.
+pub struct UnsizedRc {
+    ptr: *const (),
+    len: usize,
+    type_id: TypeId,
+    drop: unsafe fn(*const (), usize),
+    strong_count: unsafe fn(*const (), usize) -> usize,
+}
+
+impl UnsizedRc {
+    /// `type_id` is the type the value is stored as,
+    /// which must have the same layout as `[E]`.
+    #[inline]
+    fn new<E>(v: Rc<[E]>, type_id: TypeId) -> Self {
+        unsafe fn drop_slice<E>(ptr: *const (), len: usize) {
+            drop(Rc::from_raw(ptr::slice_from_raw_parts(
+                ptr as *const E,
+                len,
+            )));
+        }
+
+        unsafe fn strong_count<E>(ptr: *const (), len: usize) -> usize {
+            let v = ManuallyDrop::new(Rc::from_raw(ptr::slice_from_raw_parts(
+                ptr as *const E,
+                len,
+            )));
+            Rc::strong_count(&*v)
+        }
+
+        let len = v.len();
+        let ptr = Rc::into_raw(v) as *const E as *const ();
+
+        Self {
+            ptr,
+            len,
+            type_id,
+            drop: drop_slice::<E>,
+            strong_count: strong_count::<E>,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The value must have been created from an `Rc<[E]>`.
+    #[inline]
+    unsafe fn as_slice<E>(&self) -> ManuallyDrop<Rc<[E]>> {
+        ManuallyDrop::new(Rc::from_raw(ptr::slice_from_raw_parts(
+            self.ptr as *const E,
+            self.len,
+        )))
+    }
+
+    /// # Safety
+    ///
+    /// The value must have been created from an `Rc<[E]>`.
+    #[inline]
+    unsafe fn into_slice<E>(self) -> Rc<[E]> {
+        let v = ManuallyDrop::into_inner(self.as_slice());
+        std::mem::forget(self);
+        v
+    }
+}
+
+impl Drop for UnsizedRc {
+    fn drop(&mut self) {
+        unsafe { (self.drop)(self.ptr, self.len) }
+    }
+}
+
+/// Types which can be stored in the global store.
+///
+/// Implemented for every sized `T: Any`, [`str`] and `[E]`.
+/// It can not be implemented outside this crate.
+pub trait Forgettable: Any {
+    #[doc(hidden)]
+    fn into_value(v: Rc<Self>) -> Value;
+
+    #[doc(hidden)]
+    fn from_value(v: Value) -> Result<Rc<Self>, Value>;
+
+    #[doc(hidden)]
+    fn from_value_ref(v: &Value) -> Option<Rc<Self>>;
+}
+
+impl<T: Any> Forgettable for T {
+    #[inline]
+    fn into_value(v: Rc<Self>) -> Value {
+        Value::Sized(v)
+    }
+
+    #[inline]
+    fn from_value(v: Value) -> Result<Rc<Self>, Value> {
+        match v {
+            Value::Sized(v) => v.downcast().map_err(Value::Sized),
+            v => Err(v),
+        }
+    }
+
+    #[inline]
+    fn from_value_ref(v: &Value) -> Option<Rc<Self>> {
+        match v {
+            Value::Sized(v) => Rc::clone(v).downcast().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl<E: Any> Forgettable for [E] {
+    #[inline]
+    fn into_value(v: Rc<Self>) -> Value {
+        Value::Unsized(UnsizedRc::new(v, TypeId::of::<Self>()))
+    }
+
+    #[inline]
+    fn from_value(v: Value) -> Result<Rc<Self>, Value> {
+        match v {
+            Value::Unsized(v) if v.type_id == TypeId::of::<Self>() => Ok(unsafe { v.into_slice() }),
+            v => Err(v),
+        }
+    }
+
+    #[inline]
+    fn from_value_ref(v: &Value) -> Option<Rc<Self>> {
+        match v {
+            Value::Unsized(v) if v.type_id == TypeId::of::<Self>() => {
+                Some(Rc::clone(&*unsafe { v.as_slice() }))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Forgettable for str {
+    #[inline]
+    fn into_value(v: Rc<Self>) -> Value {
+        let bytes = unsafe { Rc::from_raw(Rc::into_raw(v) as *const [u8]) };
+        Value::Unsized(UnsizedRc::new(bytes, TypeId::of::<Self>()))
+    }
+
+    #[inline]
+    fn from_value(v: Value) -> Result<Rc<Self>, Value> {
+        match v {
+            Value::Unsized(v) if v.type_id == TypeId::of::<Self>() => {
+                let bytes = unsafe { v.into_slice::<u8>() };
+                Ok(unsafe { Rc::from_raw(Rc::into_raw(bytes) as *const str) })
+            }
+            v => Err(v),
+        }
+    }
+
+    #[inline]
+    fn from_value_ref(v: &Value) -> Option<Rc<Self>> {
+        match v {
+            Value::Unsized(v) if v.type_id == TypeId::of::<Self>() => {
+                let bytes = Rc::clone(&*unsafe { v.as_slice::<u8>() });
+                Some(unsafe { Rc::from_raw(Rc::into_raw(bytes) as *const str) })
+            }
+            _ => None,
+        }
+    }
+}
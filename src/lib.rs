@@ -1,6 +1,16 @@
+mod caps;
 mod global;
+mod meta;
+mod quota;
+mod static_forgotten;
+mod trace;
 
+pub use caps::*;
 pub use global::*;
+pub use meta::*;
+pub use quota::*;
+pub use static_forgotten::*;
+pub use trace::*;
 
 #[cfg(feature = "custom")]
 mod custom;
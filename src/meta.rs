@@ -0,0 +1,103 @@
+use std::{fmt::Display, time::Instant};
+
+/// A tag attached to entries with [`Meta::with_tag`], to find them again with
+/// `keys_with_tag` or free them with `free_by_tag`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Tag {
+    Str(String),
+    Int(u64),
+}
+
+impl From<&str> for Tag {
+    #[inline]
+    fn from(s: &str) -> Self {
+        Tag::Str(s.to_string())
+    }
+}
+
+impl From<String> for Tag {
+    #[inline]
+    fn from(s: String) -> Self {
+        Tag::Str(s)
+    }
+}
+
+impl From<u64> for Tag {
+    #[inline]
+    fn from(n: u64) -> Self {
+        Tag::Int(n)
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tag::Str(s) => Display::fmt(s, f),
+            Tag::Int(n) => Display::fmt(n, f),
+        }
+    }
+}
+
+/// Metadata of an entry, given when it is forgotten.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Meta {
+    tags: Vec<Tag>,
+    owner: Option<u64>,
+    created: Instant,
+}
+
+impl Meta {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            tags: Vec::new(),
+            owner: None,
+            created: Instant::now(),
+        }
+    }
+
+    #[inline]
+    pub fn with_tag(mut self, tag: impl Into<Tag>) -> Self {
+        let tag = tag.into();
+
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+
+        self
+    }
+
+    #[inline]
+    pub fn with_owner(mut self, owner: u64) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    #[inline]
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    #[inline]
+    pub fn owner(&self) -> Option<u64> {
+        self.owner
+    }
+
+    /// When the entry was forgotten.
+    #[inline]
+    pub fn created(&self) -> Instant {
+        self.created
+    }
+
+    #[inline]
+    pub(crate) fn stamp(mut self) -> Self {
+        self.created = Instant::now();
+        self
+    }
+}
+
+impl Default for Meta {
+    fn default() -> Self {
+        Self::new()
+    }
+}
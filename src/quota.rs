@@ -0,0 +1,219 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+/// Limits on the entries forgotten with a [`Meta`](crate::Meta) of a given owner.
+///
+/// Only entries forgotten with `forget_with_meta` and the like have an owner.
+/// They are charged when forgotten, and charged again with the size of the new value
+/// when it is replaced through an `OccupiedEntry`, which panics (or, with `try_insert`, fails)
+/// if that exceeds the quota. Values mutated in place are not measured again.
+/// Quotas do not apply to other ways of forgetting values, such as `forget` or `forget_at`,
+/// so code which must be limited should only be given access to the former.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quota {
+    max_entries: Option<usize>,
+    max_bytes: Option<usize>,
+}
+
+impl Quota {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_max_entries(mut self, max: usize) -> Self {
+        self.max_entries = Some(max);
+        self
+    }
+
+    /// Limits the approximate size of the values,
+    /// as measured by the store's size callback.
+    #[inline]
+    pub fn with_max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = Some(max);
+        self
+    }
+
+    #[inline]
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    #[inline]
+    pub fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    #[inline]
+    fn allows(&self, usage: Usage) -> bool {
+        !matches!(self.max_entries, Some(max) if usage.entries > max)
+            && !matches!(self.max_bytes, Some(max) if usage.bytes > max)
+    }
+}
+
+/// The entries of an owner and their approximate size in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Usage {
+    entries: usize,
+    bytes: usize,
+}
+
+impl Usage {
+    #[inline]
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    #[inline]
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Returned when forgetting a value would take its owner over its [`Quota`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    owner: u64,
+    quota: Quota,
+    usage: Usage,
+}
+
+impl QuotaExceeded {
+    #[inline]
+    pub fn owner(&self) -> u64 {
+        self.owner
+    }
+
+    #[inline]
+    pub fn quota(&self) -> Quota {
+        self.quota
+    }
+
+    /// The usage of the owner, without the rejected value.
+    #[inline]
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+}
+
+impl Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "quota of owner {} exceeded", self.owner)
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Quotas of a store, and the usage of the owners of its entries.
+#[derive(Debug)]
+pub(crate) struct Quotas<K> {
+    quotas: BTreeMap<u64, Quota>,
+    usage: BTreeMap<u64, Usage>,
+    /// The owner and size each entry was charged with.
+    charged: BTreeMap<K, (u64, usize)>,
+}
+
+impl<K: Ord> Quotas<K> {
+    #[inline]
+    pub(crate) fn set(&mut self, owner: u64, quota: Option<Quota>) {
+        match quota {
+            Some(quota) => self.quotas.insert(owner, quota),
+            None => self.quotas.remove(&owner),
+        };
+    }
+
+    #[inline]
+    pub(crate) fn usage(&self, owner: u64) -> Usage {
+        self.usage.get(&owner).copied().unwrap_or_default()
+    }
+
+    /// Checks that an entry of `bytes` fits in the quota of `owner`.
+    #[inline]
+    pub(crate) fn check(&self, owner: u64, bytes: usize) -> Result<(), QuotaExceeded> {
+        let quota = match self.quotas.get(&owner) {
+            Some(quota) => *quota,
+            None => return Ok(()),
+        };
+        let usage = self.usage(owner);
+        let after = Usage {
+            entries: usage.entries.saturating_add(1),
+            bytes: usage.bytes.saturating_add(bytes),
+        };
+
+        if quota.allows(after) {
+            Ok(())
+        } else {
+            Err(QuotaExceeded {
+                owner,
+                quota,
+                usage,
+            })
+        }
+    }
+
+    #[inline]
+    pub(crate) fn charge(&mut self, k: K, owner: u64, bytes: usize) {
+        let usage = self.usage.entry(owner).or_default();
+        usage.entries += 1;
+        usage.bytes = usage.bytes.saturating_add(bytes);
+        self.charged.insert(k, (owner, bytes));
+    }
+
+    /// Charges the owner of `k`, if any, with `bytes` instead of the size it was charged with,
+    /// unless that would take the owner over its quota.
+    #[inline]
+    pub(crate) fn recharge(&mut self, k: &K, bytes: usize) -> Result<(), QuotaExceeded> {
+        let (owner, old) = match self.charged.get_mut(k) {
+            Some(charged) => charged,
+            None => return Ok(()),
+        };
+        let usage = self.usage.entry(*owner).or_default();
+        let after = Usage {
+            entries: usage.entries,
+            bytes: (usage.bytes - *old).saturating_add(bytes),
+        };
+
+        if bytes > *old {
+            if let Some(quota) = self.quotas.get(owner) {
+                if !quota.allows(after) {
+                    return Err(QuotaExceeded {
+                        owner: *owner,
+                        quota: *quota,
+                        usage: *usage,
+                    });
+                }
+            }
+        }
+
+        *usage = after;
+        *old = bytes;
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn refund(&mut self, k: &K) {
+        let (owner, bytes) = match self.charged.remove(k) {
+            Some(charged) => charged,
+            None => return,
+        };
+
+        if let Some(usage) = self.usage.get_mut(&owner) {
+            usage.entries -= 1;
+            usage.bytes -= bytes;
+
+            if usage.entries == 0 {
+                self.usage.remove(&owner);
+            }
+        }
+    }
+}
+
+impl<K> Default for Quotas<K> {
+    fn default() -> Self {
+        Self {
+            quotas: BTreeMap::new(),
+            usage: BTreeMap::new(),
+            charged: BTreeMap::new(),
+        }
+    }
+}
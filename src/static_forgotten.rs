@@ -0,0 +1,146 @@
+use std::fmt::{Debug, Display};
+
+/// Returned by [`StaticForgotten::forget`] when every slot is taken.
+/// The rejected value is handed back.
+pub struct Full<T>(pub T);
+
+impl<T> Full<T> {
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Debug for Full<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Full(..)")
+    }
+}
+
+impl<T> Display for Full<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Forgotten is full")
+    }
+}
+
+impl<T> std::error::Error for Full<T> {}
+
+/// A store holding at most `N` values inline, without any allocation.
+///
+/// Keys are in `1..=N`, so `0` is never a valid key.
+pub struct StaticForgotten<T, const N: usize> {
+    cur: usize,
+    len: usize,
+    slots: [Option<T>; N],
+}
+
+impl<T, const N: usize> StaticForgotten<T, N> {
+    const EMPTY: Option<T> = None;
+
+    pub const fn new() -> Self {
+        Self {
+            cur: 0,
+            len: 0,
+            slots: [Self::EMPTY; N],
+        }
+    }
+
+    #[inline]
+    fn find_available_key(&mut self) -> Option<usize> {
+        if self.len == N {
+            return None;
+        }
+
+        loop {
+            let k = self.cur % N + 1;
+            self.cur = k;
+
+            if self.slots[k - 1].is_none() {
+                return Some(k);
+            }
+        }
+    }
+
+    #[inline]
+    fn slot(&self, k: &usize) -> Option<&Option<T>> {
+        k.checked_sub(1).and_then(|i| self.slots.get(i))
+    }
+
+    #[inline]
+    fn slot_mut(&mut self, k: &usize) -> Option<&mut Option<T>> {
+        k.checked_sub(1).and_then(move |i| self.slots.get_mut(i))
+    }
+
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn forget(&mut self, v: T) -> Result<usize, Full<T>> {
+        let k = match self.find_available_key() {
+            Some(k) => k,
+            None => return Err(Full(v)),
+        };
+
+        self.slots[k - 1] = Some(v);
+        self.len += 1;
+
+        Ok(k)
+    }
+
+    #[inline]
+    pub fn try_free(&mut self, k: &usize) -> bool {
+        self.try_take(k).is_some()
+    }
+
+    #[inline]
+    pub fn try_get(&self, k: &usize) -> Option<&T> {
+        self.slot(k).and_then(Option::as_ref)
+    }
+
+    #[inline]
+    pub fn try_get_mut(&mut self, k: &usize) -> Option<&mut T> {
+        self.slot_mut(k).and_then(Option::as_mut)
+    }
+
+    #[inline]
+    pub fn try_take(&mut self, k: &usize) -> Option<T> {
+        let v = self.slot_mut(k).and_then(Option::take);
+
+        if v.is_some() {
+            self.len -= 1;
+        }
+
+        v
+    }
+}
+
+impl<T, const N: usize> Default for StaticForgotten<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for StaticForgotten<T, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.slots
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, v)| v.as_ref().map(|v| (i + 1, v))),
+            )
+            .finish()
+    }
+}
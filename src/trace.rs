@@ -0,0 +1,42 @@
+use std::any::Any;
+
+use crate::SharedForgottenKey;
+
+/// Reports the keys a stored value refers to, so that entries which are no longer
+/// reachable can be freed by [`collect`](crate::collect) or `Forgotten::collect`.
+///
+/// `trace` is called while the store is borrowed, so it must not use the store.
+pub trait Trace<K = usize> {
+    fn trace(&self, tracer: &mut Tracer<K>);
+}
+
+pub struct Tracer<K = usize> {
+    keys: Vec<K>,
+}
+
+impl<K> Tracer<K> {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self { keys: Vec::new() }
+    }
+
+    #[inline]
+    pub(crate) fn take_keys(&mut self) -> Vec<K> {
+        std::mem::take(&mut self.keys)
+    }
+
+    #[inline]
+    pub fn visit(&mut self, k: &K)
+    where
+        K: Clone,
+    {
+        self.keys.push(k.clone());
+    }
+}
+
+impl Tracer {
+    #[inline]
+    pub fn visit_key<T: ?Sized + Any>(&mut self, k: &SharedForgottenKey<T>) {
+        self.keys.push(*k.as_usize());
+    }
+}
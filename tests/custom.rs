@@ -66,3 +66,611 @@ fn use_custom_forgotten_ref_cell() {
         assert_eq!(F.with(|f| f.try_get(&k)).unwrap().deref(), &0);
     }
 }
+
+#[test]
+fn use_custom_forgotten_box() {
+    let mut f = Forgotten::<u8, String, Box<String>>::new();
+
+    let k = f.forget("hello".to_string());
+    assert_eq!(k, 1);
+
+    f.try_mut(&k).unwrap().push_str(" world");
+    assert_eq!(**f.try_ref(&k).unwrap(), "hello world");
+
+    let b: Box<String> = f.try_take(&k).unwrap();
+    assert_eq!(*b, "hello world");
+    assert!(f.try_ref(&k).is_none());
+    assert!(f.try_mut(&k).is_none());
+
+    let k = f.forget_ptr(Box::new("boxed".to_string()));
+    assert_eq!(k, 2);
+    assert!(f.try_free(&k));
+}
+
+#[test]
+fn use_custom_forgotten_arc() {
+    use std::sync::Arc;
+
+    let mut f = Forgotten::<u8, dyn Display + Send + Sync, Arc<dyn Display + Send + Sync>>::new();
+
+    let k = f.forget_arc(Arc::new(1));
+    let v = f.try_get(&k).unwrap();
+    let v = std::thread::spawn(move || v.to_string()).join().unwrap();
+    assert_eq!(v, "1");
+
+    let mut f = Forgotten::<u8, i32, Arc<i32>>::new();
+    let (k, v) = f.forget_and_get(2);
+    assert!(Arc::ptr_eq(&f.try_take(&k).unwrap(), &v));
+}
+
+#[test]
+fn use_custom_forgotten_int_hasher() {
+    use forgotten::BuildIntHasher;
+
+    let mut f = Forgotten::<u32, i32, Rc<i32>, BuildIntHasher>::new();
+    let keys: Vec<_> = (0..100).map(|i| f.forget(i)).collect();
+    for (i, k) in keys.iter().enumerate() {
+        assert_eq!(*f.try_get(k).unwrap(), i as i32);
+    }
+
+    let f = ForgottenRefCell::<u32, str, Rc<str>, _>::with_hasher(BuildIntHasher::default());
+    let k = f.forget_rc("hello".into());
+    assert_eq!(&*f.try_take(&k).unwrap(), "hello");
+}
+
+#[test]
+fn use_custom_forgotten_entry() {
+    use forgotten::Entry;
+
+    let mut f = Forgotten::<u8, String, Box<String>>::new();
+    let k = f.forget("a".to_string());
+
+    match f.entry(&k).unwrap() {
+        Entry::Occupied(mut e) => {
+            assert_eq!(*e.key(), k);
+            e.value_mut().push('b');
+            assert_eq!(*e.insert("c".to_string()), "ab");
+        }
+        Entry::Vacant(_) => unreachable!(),
+    }
+    assert_eq!(**f.try_ref(&k).unwrap(), "c");
+
+    f.entry(&k)
+        .unwrap()
+        .and_modify(|v| v.push('d'))
+        .or_insert_with(|| unreachable!());
+    assert_eq!(**f.try_ref(&k).unwrap(), "cd");
+
+    assert!(matches!(f.entry(&5).unwrap(), Entry::Vacant(_)));
+    f.entry(&5)
+        .unwrap()
+        .and_modify(|_| unreachable!())
+        .or_insert_with(|| "e".to_string());
+    assert_eq!(**f.try_ref(&5).unwrap(), "e");
+    assert_eq!(f.forget("f".to_string()), 2);
+
+    assert_eq!(*f.entry(&5).unwrap().remove().unwrap(), "e");
+    assert!(f.entry(&5).unwrap().remove().is_none());
+    assert!(f.try_ref(&5).is_none());
+
+    assert!(f.entry(&6).unwrap().insert("g".to_string()).is_none());
+    assert_eq!(*f.entry(&6).unwrap().insert("h".to_string()).unwrap(), "g");
+    assert_eq!(**f.try_ref(&6).unwrap(), "h");
+    assert!(f.entry(&0).is_none());
+}
+
+#[test]
+fn custom_forgotten_entry_bookkeeping() {
+    use forgotten::{Meta, Quota, Tag};
+    use std::sync::{Arc, Mutex};
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut f = Forgotten::<u8, u8>::new();
+    let l = Arc::clone(&log);
+    f.on_insert(move |k, _| l.lock().unwrap().push(format!("insert {}", k)));
+    let l = Arc::clone(&log);
+    f.on_remove(move |k| l.lock().unwrap().push(format!("remove {}", k)));
+
+    f.set_quota(1, Quota::new().with_max_entries(1));
+    let k = f
+        .try_forget_with_meta(1, Meta::new().with_owner(1).with_tag("t"))
+        .unwrap();
+    f.pin(&k).unwrap();
+    f.entry(&k).unwrap().remove().unwrap();
+
+    assert_eq!(f.usage(1).entries(), 0);
+    assert!(f.keys_with_tag(&Tag::from("t")).is_empty());
+    assert!(!f.is_pinned(&k));
+
+    f.entry(&k).unwrap().or_insert(2);
+    assert!(f.meta(&k).unwrap().is_none());
+    assert!(f.try_forget_with_meta(3, Meta::new().with_owner(1)).is_ok());
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["insert 1", "remove 1", "insert 1", "insert 2"]
+    );
+}
+
+#[test]
+fn use_custom_forgotten_reserved() {
+    let mut f = Forgotten::<u8, &str>::new();
+
+    assert!(f.reserve(1..=1));
+    assert!(f.reserve(3..=5));
+    assert!(!f.reserve(5..=6));
+    assert!(!f.reserve(0..=1));
+    #[allow(clippy::reversed_empty_ranges)]
+    let empty = 8..=7;
+    assert!(!f.reserve(empty));
+    assert!(f.is_reserved(&4));
+    assert!(!f.is_reserved(&6));
+
+    f.forget_at(1, "stdin").unwrap();
+    assert_eq!(f.forget_at(1, "stdout").unwrap_err().into_inner(), "stdout");
+    assert_eq!(*f.try_get(&1).unwrap(), "stdin");
+
+    assert_eq!(f.forget("a"), 2);
+    assert_eq!(f.forget("b"), 6);
+
+    assert!(!f.release_reservation(&(3..=4)));
+    assert!(f.release_reservation(&(3..=5)));
+    assert!(!f.release_reservation(&(3..=5)));
+
+    assert_eq!(f.forget("c"), 7);
+
+    assert!(f.reserve(8..=u8::MAX));
+    assert_eq!(f.forget("d"), 3);
+    assert_eq!(f.forget("e"), 4);
+    assert_eq!(f.forget("f"), 5);
+
+    assert!(f.try_free(&2));
+    assert_eq!(f.forget("g"), 2);
+}
+
+#[test]
+#[should_panic(expected = "Forgotten is full")]
+fn use_custom_forgotten_all_reserved() {
+    let mut f = Forgotten::<i8, i8>::new();
+    assert!(f.reserve(i8::MIN..=-1));
+    assert!(f.reserve(2..=i8::MAX));
+
+    assert_eq!(f.forget(0), 1);
+    f.forget(1);
+}
+
+#[test]
+fn use_custom_forgotten_with() {
+    let mut f = Forgotten::<u8, (u8, &str)>::new();
+    let k = f.forget_with(|k| (*k, "a"));
+    assert_eq!(*f.try_get(&k).unwrap(), (1, "a"));
+
+    struct Node {
+        key: u16,
+        this: std::rc::Weak<Node>,
+    }
+
+    let mut f = Forgotten::<u16, Node>::new();
+    let k = f.forget_cyclic(|key, this| Node {
+        key: *key,
+        this: this.clone(),
+    });
+    let node = f.try_get(&k).unwrap();
+    assert_eq!(node.key, k);
+    assert!(Rc::ptr_eq(&node.this.upgrade().unwrap(), &node));
+
+    let mut f = Forgotten::<u16, u16, std::sync::Arc<u16>>::new();
+    let k = f.forget_cyclic(|key, _| *key + 1);
+    assert_eq!(*f.try_get(&k).unwrap(), 2);
+}
+
+#[test]
+fn use_custom_forgotten_ref_cell_with() {
+    thread_local! {
+        static F: ForgottenRefCell<u8, (u8, u8)> = ForgottenRefCell::new()
+    }
+
+    let k = F.with(|f| {
+        f.forget_with(|k| {
+            let other = F.with(|f| f.forget((0, 0)));
+            (*k, other)
+        })
+    });
+    assert_eq!(k, 1);
+    assert_eq!(*F.with(|f| f.try_get(&k)).unwrap(), (1, 2));
+
+    let r = std::panic::catch_unwind(|| F.with(|f| f.forget_with(|_| panic!("oops"))));
+    assert!(r.is_err());
+    assert_eq!(F.with(|f| f.forget((0, 0))), 4);
+}
+
+#[test]
+fn use_any_forgotten() {
+    use std::any::Any;
+
+    let mut f = Forgotten::<u32, dyn Any>::new();
+
+    let a = f.forget_any(1u8);
+    let b = f.forget_any("b".to_string());
+    assert!(a.is::<u8>());
+    assert!(!a.is::<String>());
+    assert_eq!(b.type_name(), std::any::type_name::<String>());
+
+    assert_eq!(*f.try_get_as::<u8>(a.key()).unwrap(), 1);
+    assert!(f.try_get_as::<u16>(a.key()).is_none());
+
+    assert!(f.try_take_as::<u8>(b.key()).is_none());
+    assert_eq!(*f.try_take_as::<String>(b.key()).unwrap(), "b");
+    assert!(f.try_get_as::<String>(b.key()).is_none());
+
+    // the key of a freed entry does not give the value reusing it
+    assert_eq!(*f.try_take_any::<u8>(&a).unwrap(), 1);
+    assert!(f.forget_ptr_at(*a.key(), Rc::new(2u16)).is_ok());
+    assert!(f.try_get_any::<u16>(&a).is_none());
+    assert!(f.try_take_any::<u16>(&a).is_none());
+    assert_eq!(*f.try_get_as::<u16>(a.key()).unwrap(), 2);
+
+    let f = ForgottenRefCell::<u32, dyn Any>::new();
+    let k = f.forget_any(vec![1, 2]);
+    assert!(f.try_take_as::<Vec<u8>>(k.key()).is_none());
+    assert_eq!(*f.try_get_as::<Vec<i32>>(k.key()).unwrap(), [1, 2]);
+    assert!(f.try_get_any::<Vec<u8>>(&k).is_none());
+    assert_eq!(*f.try_get_any::<Vec<i32>>(&k).unwrap(), [1, 2]);
+    assert_eq!(*f.try_take_any::<Vec<i32>>(&k).unwrap(), [1, 2]);
+
+    // taking only requires the alias to have TAKE
+    let k = f.forget_any(3u8);
+    let take = f.restrict(k.key(), forgotten::Caps::TAKE).unwrap();
+    assert!(f.try_take_as::<u16>(&take).is_none());
+    assert_eq!(*f.try_take_as::<u8>(&take).unwrap(), 3);
+
+    // insert hooks get the type of the value rather than `dyn Any`
+    let names = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut f = Forgotten::<u32, dyn Any>::new();
+    let n = std::sync::Arc::clone(&names);
+    f.on_insert(move |_, type_name| n.lock().unwrap().push(type_name));
+    f.forget_any(1u8);
+    assert_eq!(*names.lock().unwrap(), ["u8"]);
+}
+
+#[test]
+fn collect_custom_forgotten() {
+    use forgotten::{Trace, Tracer};
+
+    struct Node(Vec<u8>);
+
+    impl Trace<u8> for Node {
+        fn trace(&self, tracer: &mut Tracer<u8>) {
+            for k in &self.0 {
+                tracer.visit(k);
+            }
+        }
+    }
+
+    let mut f = Forgotten::<u8, Node>::new();
+    let a = f.forget(Node(vec![2]));
+    let b = f.forget(Node(vec![1, 3]));
+    let c = f.forget(Node(vec![]));
+    let d = f.forget(Node(vec![4]));
+    assert_eq!((a, b, c, d), (1, 2, 3, 4));
+
+    assert!(f.pin(&a).is_ok());
+    assert!(f.pin(&9).is_err());
+    assert_eq!(f.collect(), [4]);
+    assert!(f.try_get(&c).is_some());
+
+    assert!(f.unpin(&a).is_ok());
+    assert_eq!(f.collect(), [1, 2, 3]);
+    assert!(f.try_get(&a).is_none());
+
+    // a removed pinned entry does not pin an entry reusing its key
+    let k = f.forget(Node(vec![]));
+    f.pin(&k).unwrap();
+    f.entry(&k).unwrap().remove();
+    f.forget_at(k, Node(vec![])).unwrap();
+    assert_eq!(f.collect(), [k]);
+
+    let f = ForgottenRefCell::<u8, Node>::new();
+    let a = f.forget(Node(vec![]));
+    f.pin(&a).unwrap();
+    f.forget(Node(vec![a]));
+    assert_eq!(f.collect(), [2]);
+
+    thread_local! {
+        static STORE: ForgottenRefCell<u8, Linked> = ForgottenRefCell::new();
+    }
+
+    // frees the entry it links to when dropped
+    struct Linked(Option<u8>);
+
+    impl Trace<u8> for Linked {
+        fn trace(&self, _: &mut Tracer<u8>) {}
+    }
+
+    impl Drop for Linked {
+        fn drop(&mut self) {
+            if let Some(k) = self.0 {
+                STORE.with(|f| f.try_free(&k));
+            }
+        }
+    }
+
+    STORE.with(|f| {
+        let a = f.forget(Linked(None));
+        f.pin(&a).unwrap();
+        f.forget(Linked(Some(a)));
+        assert_eq!(f.collect(), [2]);
+        assert!(f.try_get(&a).is_none());
+    });
+}
+
+#[test]
+fn transient_custom_forgotten() {
+    let mut f = Forgotten::<u8, u8>::new();
+    f.set_epoch_retention(1);
+
+    let a = f.forget_transient(1);
+    let b = f.forget(2);
+    assert_eq!(f.advance_epoch(), 0);
+    let c = f.forget_transient(3);
+    assert_eq!(f.advance_epoch(), 1);
+    assert_eq!(f.epoch(), 2);
+    assert!(f.try_get(&a).is_none());
+    assert!(f.try_get(&b).is_some());
+    assert!(f.try_get(&c).is_some());
+
+    assert!(f.try_free(&c));
+    let d = f.forget_transient(4);
+    assert_eq!(f.advance_epoch(), 0);
+    assert_eq!(f.advance_epoch(), 1);
+    assert!(f.try_get(&d).is_none());
+
+    let f = ForgottenRefCell::<u8, u8>::new();
+    let k = f.forget_transient(0);
+    assert_eq!(f.advance_epoch(), 1);
+    assert!(f.try_get(&k).is_none());
+
+    thread_local! {
+        static STORE: ForgottenRefCell<u8, Expiring> = ForgottenRefCell::new();
+    }
+
+    // forgets another value when dropped, if it should
+    struct Expiring(bool);
+
+    impl Drop for Expiring {
+        fn drop(&mut self) {
+            if self.0 {
+                STORE.with(|f| f.forget_transient(Expiring(false)));
+            }
+        }
+    }
+
+    STORE.with(|f| {
+        f.forget_transient(Expiring(true));
+        assert_eq!(f.advance_epoch(), 1);
+        assert_eq!(f.advance_epoch(), 1);
+    });
+
+    // a transient entry removed through an Entry, whose key is reused
+    let mut f = Forgotten::<u8, u8>::new();
+    let k = f.forget_transient(5);
+    f.entry(&k).unwrap().remove();
+    f.forget_at(k, 7).unwrap();
+    assert_eq!(f.advance_epoch(), 0);
+    assert_eq!(*f.try_get(&k).unwrap(), 7);
+}
+
+#[test]
+fn custom_hooks() {
+    use std::sync::{Arc, Mutex};
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    let mut f = Forgotten::<u8, u8>::new();
+    let l = Arc::clone(&log);
+    f.on_insert(move |k, type_name| {
+        l.lock()
+            .unwrap()
+            .push(format!("insert {} {}", k, type_name))
+    });
+    let l = Arc::clone(&log);
+    f.on_remove(move |k| l.lock().unwrap().push(format!("remove {}", k)));
+    let l = Arc::clone(&log);
+    f.on_value_dropped(move |k| l.lock().unwrap().push(format!("drop {}", k)));
+
+    let k = f.forget(1);
+    f.forget_at(9, 2).unwrap();
+    f.try_take(&k);
+    f.try_free(&9);
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "insert 1 u8",
+            "insert 9 u8",
+            "remove 1",
+            "remove 9",
+            "drop 9"
+        ]
+    );
+
+    thread_local! {
+        static STORE: ForgottenRefCell<u8, u8> = ForgottenRefCell::new();
+    }
+
+    // hooks of a ForgottenRefCell need not be Send, and may use it
+    let log = Rc::new(std::cell::RefCell::new(Vec::new()));
+
+    STORE.with(|f| {
+        let l = Rc::clone(&log);
+        f.on_insert(move |k, _| {
+            let v = STORE.with(|f| f.try_get(k)).unwrap();
+            l.borrow_mut().push(format!("inserted {}", v));
+        });
+        let l = Rc::clone(&log);
+        f.on_value_dropped(move |k| {
+            let freed = STORE.with(|f| f.try_get(k)).is_none();
+            l.borrow_mut().push(format!("dropped {}", freed));
+        });
+
+        let k = f.forget(7);
+        assert!(f.try_free(&k));
+    });
+
+    assert_eq!(*log.borrow(), ["inserted 7", "dropped true"]);
+}
+
+#[test]
+fn custom_forgotten_tags() {
+    use forgotten::{Meta, Tag};
+
+    let mut f = Forgotten::<u8, u8>::new();
+    let a = f.forget_with_meta(1, Meta::new().with_tag("a").with_tag("b"));
+    let b = f.forget_with_meta(2, Meta::new().with_tag("b"));
+    let c = f.forget(3);
+
+    assert_eq!(f.meta(&a).unwrap().unwrap().tags().len(), 2);
+    assert!(f.meta(&c).unwrap().is_none());
+    assert_eq!(f.keys_with_tag(&Tag::from("b")), [a, b]);
+
+    assert!(f.try_free(&a));
+    assert!(f.keys_with_tag(&Tag::from("a")).is_empty());
+    assert_eq!(f.free_by_tag(&Tag::from("b")), [b]);
+    assert!(f.try_get(&b).is_none());
+    assert_eq!(*f.try_get(&c).unwrap(), 3);
+
+    // a tagged entry removed through an Entry, whose key is reused
+    let k = f.forget_with_meta(6, Meta::new().with_tag("c"));
+    f.entry(&k).unwrap().remove();
+    f.forget_at(k, 42).unwrap();
+    assert!(f.free_by_tag(&Tag::from("c")).is_empty());
+    assert_eq!(*f.try_get(&k).unwrap(), 42);
+
+    let f = ForgottenRefCell::<u8, u8>::new();
+    let k = f.forget_with_meta(4, Meta::new().with_owner(1).with_tag(5));
+    assert_eq!(f.meta(&k).unwrap().unwrap().owner(), Some(1));
+    assert_eq!(f.free_by_tag(&Tag::Int(5)), [k]);
+    assert!(f.meta(&k).is_err());
+}
+
+#[test]
+fn custom_forgotten_quotas() {
+    use forgotten::{Meta, Quota};
+
+    let mut f = Forgotten::<u8, str, Box<str>>::new();
+    f.set_quota(1, Quota::new().with_max_bytes(8));
+
+    let a = f
+        .try_forget_ptr_with_meta("hello".into(), Meta::new().with_owner(1))
+        .unwrap();
+    let e = f
+        .try_forget_ptr_with_meta("world".into(), Meta::new().with_owner(1))
+        .unwrap_err();
+    assert_eq!(e.usage().bytes(), 5);
+    assert_eq!(e.quota().max_bytes(), Some(8));
+
+    f.set_size_of(|_| 1);
+    let b = f
+        .try_forget_ptr_with_meta("world".into(), Meta::new().with_owner(1))
+        .unwrap();
+    assert_eq!(f.usage(1).entries(), 2);
+    assert_eq!(f.usage(1).bytes(), 6);
+
+    f.try_free(&a);
+    f.try_take(&b);
+    assert_eq!(f.usage(1).entries(), 0);
+
+    f.set_quota(1, Quota::new().with_max_entries(1));
+    let c = f
+        .try_forget_ptr_with_meta("c".into(), Meta::new().with_owner(1))
+        .unwrap();
+    f.entry(&c).unwrap().remove();
+    assert_eq!(f.usage(1).entries(), 0);
+    assert!(f
+        .try_forget_ptr_with_meta("d".into(), Meta::new().with_owner(1))
+        .is_ok());
+
+    let mut f = Forgotten::<u8, str, Box<str>>::new();
+    f.set_quota(1, Quota::new().with_max_bytes(8));
+    let k = f
+        .try_forget_ptr_with_meta("ab".into(), Meta::new().with_owner(1))
+        .unwrap();
+    let mut e = match f.entry(&k).unwrap() {
+        forgotten::Entry::Occupied(e) => e,
+        forgotten::Entry::Vacant(_) => unreachable!(),
+    };
+    assert_eq!(&*e.insert_ptr("abcdef".into()), "ab");
+    assert!(e.try_insert_ptr("abcdefghi".into()).is_err());
+    assert_eq!(&**e.get(), "abcdef");
+    assert_eq!(f.usage(1).bytes(), 6);
+    assert_eq!(
+        f.entry(&k).unwrap().insert_ptr("a".into()).as_deref(),
+        Some("abcdef")
+    );
+    assert_eq!(f.usage(1).bytes(), 1);
+
+    let f = ForgottenRefCell::<u8, u8>::new();
+    f.set_quota(2, Quota::new().with_max_entries(1));
+    let k = f
+        .try_forget_with_meta(1, Meta::new().with_owner(2))
+        .unwrap();
+    assert!(f
+        .try_forget_with_meta(2, Meta::new().with_owner(2))
+        .is_err());
+    assert!(f.try_free(&k));
+    assert!(f.try_forget_with_meta(3, Meta::new().with_owner(2)).is_ok());
+}
+
+#[test]
+fn custom_forgotten_caps() {
+    use forgotten::Caps;
+
+    let mut f = Forgotten::<u8, u8, Box<u8>>::new();
+    let k = f.forget(1);
+    let read = f.restrict(&k, Caps::READ | Caps::DERIVE).unwrap();
+    let read_only = f.restrict(&read, Caps::ALL).unwrap();
+    assert_eq!(f.caps(&read_only), Some(Caps::READ | Caps::DERIVE));
+    assert_eq!(*f.get_checked(&read_only).unwrap(), 1);
+    assert!(f.try_mut(&read).is_none());
+    assert!(f.pin(&read).is_err());
+    assert!(f.meta(&read).unwrap().is_none());
+    assert!(f.forget_at(read, 2).is_err());
+    assert!(!f.try_free(&read));
+    assert_eq!(
+        f.take_checked(&read).unwrap_err().required(),
+        Some(Caps::TAKE)
+    );
+
+    assert!(f.entry(&read).is_none());
+    let write = f.restrict(&k, Caps::WRITE).unwrap();
+    *f.mut_checked(&write).unwrap() += 1;
+    assert!(f.get_checked(&write).is_err());
+    assert!(f.unrestrict(&write));
+    assert!(!f.unrestrict(&write));
+    assert!(f.caps(&write).is_none());
+    assert!(!f.unrestrict(&k));
+    assert_eq!(**f.try_ref(&k).unwrap(), 2);
+
+    let all = f.restrict(&k, Caps::ALL).unwrap();
+    *f.mut_checked(&all).unwrap() += 1;
+    assert_eq!(*f.entry(&all).unwrap().and_modify(|v| **v += 1).key(), k);
+    assert_eq!(**f.try_ref(&k).unwrap(), 4);
+
+    let take = f.restrict(&k, Caps::TAKE).unwrap();
+    assert_eq!(*f.take_checked(&take).unwrap(), 4);
+    assert!(f.caps(&read).is_none());
+    assert_eq!(
+        f.restrict(&k, Caps::READ).unwrap_err(),
+        forgotten::AccessError::NotFound
+    );
+
+    let f = ForgottenRefCell::<u8, u8>::new();
+    let k = f.forget(3);
+    let free = f.restrict(&k, Caps::FREE).unwrap();
+    assert!(f.get_checked(&free).is_err());
+    assert!(f.unrestrict(&free));
+    assert_eq!(
+        f.free_checked(&free).unwrap_err(),
+        forgotten::AccessError::NotFound
+    );
+    assert!(f.free_checked(&f.restrict(&k, Caps::FREE).unwrap()).is_ok());
+    assert!(f.try_get(&k).is_none());
+}
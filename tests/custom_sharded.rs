@@ -0,0 +1,114 @@
+#![cfg(feature = "custom")]
+
+use forgotten::{ForgottenMutex, ShardedForgotten};
+use std::{sync::Arc, thread, time::Instant};
+
+const THREADS: usize = 8;
+const KEYS: usize = 256;
+const ROUNDS: usize = 200;
+
+#[test]
+fn use_sharded_forgotten() {
+    let f = ShardedForgotten::<str>::with_shards(3);
+    assert_eq!(f.shards(), 4);
+
+    let keys: Vec<_> = (0..8).map(|i| f.forget_arc(i.to_string().into())).collect();
+    assert_eq!(keys, [4, 1, 2, 3, 8, 5, 6, 7]);
+    assert_eq!(f.len(), 8);
+
+    assert_eq!(&*f.try_get(&keys[1]).unwrap(), "1");
+    assert_eq!(&*f.try_take(&keys[1]).unwrap(), "1");
+    assert!(f.try_get(&keys[1]).is_none());
+    assert!(f.try_free(&keys[0]));
+    assert!(!f.try_free(&keys[0]));
+    assert_eq!(f.len(), 6);
+}
+
+/// Each thread repeatedly reads a set of preloaded entries,
+/// and forgets and takes back an entry of its own.
+fn run<F: Send + Sync + 'static>(
+    store: &Arc<F>,
+    forget: fn(&F, usize) -> usize,
+    get: fn(&F, &usize) -> Option<Arc<usize>>,
+    take: fn(&F, &usize) -> Option<Arc<usize>>,
+) {
+    let keys: Arc<Vec<_>> = Arc::new((0..KEYS).map(|i| forget(store, i)).collect());
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let store = Arc::clone(store);
+            let keys = Arc::clone(&keys);
+            thread::spawn(move || {
+                for round in 0..ROUNDS {
+                    for (i, k) in keys.iter().enumerate() {
+                        assert_eq!(*get(&store, k).unwrap(), i);
+                    }
+
+                    let v = t * ROUNDS + round;
+                    let k = forget(&store, v);
+                    assert_eq!(*take(&store, &k).unwrap(), v);
+                }
+            })
+        })
+        .collect();
+
+    for h in handles {
+        h.join().unwrap();
+    }
+
+    for (i, k) in keys.iter().enumerate() {
+        assert_eq!(*take(store, k).unwrap(), i);
+    }
+}
+
+#[test]
+fn sharded_forgotten_from_threads() {
+    let sharded = Arc::new(ShardedForgotten::<usize>::with_shards(THREADS * 4));
+    run(
+        &sharded,
+        ShardedForgotten::forget,
+        ShardedForgotten::try_get,
+        ShardedForgotten::try_take,
+    );
+    assert_eq!(sharded.len(), 0);
+
+    run(
+        &Arc::new(ForgottenMutex::<usize, usize>::new()),
+        ForgottenMutex::forget,
+        ForgottenMutex::try_get,
+        ForgottenMutex::try_take,
+    );
+}
+
+/// Run with `cargo test --all-features -- --ignored --nocapture` to compare the stores.
+#[test]
+#[ignore]
+fn sharded_forgotten_throughput() {
+    // reads of the preloaded entries, and a forget and a take per round
+    let ops = (THREADS * ROUNDS * (KEYS + 2)) as f64;
+    let ops_per_sec = |run: &dyn Fn()| {
+        let start = Instant::now();
+        run();
+        ops / start.elapsed().as_secs_f64()
+    };
+
+    let sharded = ops_per_sec(&|| {
+        run(
+            &Arc::new(ShardedForgotten::<usize>::with_shards(THREADS * 4)),
+            ShardedForgotten::forget,
+            ShardedForgotten::try_get,
+            ShardedForgotten::try_take,
+        )
+    });
+    let mutex = ops_per_sec(&|| {
+        run(
+            &Arc::new(ForgottenMutex::<usize, usize>::new()),
+            ForgottenMutex::forget,
+            ForgottenMutex::try_get,
+            ForgottenMutex::try_take,
+        )
+    });
+
+    println!("ShardedForgotten: {:.0} ops/s", sharded);
+    println!("ForgottenMutex:   {:.0} ops/s", mutex);
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "custom")]
+
+use forgotten::{ForgottenMutex, ForgottenRwLock};
+use std::{sync::Arc, thread};
+
+#[test]
+fn use_custom_forgotten_mutex() {
+    static F: ForgottenMutex<u32, String> = ForgottenMutex::new();
+
+    assert!(F.try_get(&1).is_none());
+    assert!(!F.try_free(&1));
+
+    let k = F.forget("hello".to_string());
+    assert_eq!(k, 1);
+    assert_eq!(*F.try_get(&k).unwrap(), "hello");
+
+    let (k2, v2) = F.forget_and_get("world".to_string());
+    assert_eq!(k2, 2);
+    assert!(Arc::ptr_eq(&F.try_get(&k2).unwrap(), &v2));
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| thread::spawn(move || F.forget(i.to_string())))
+        .collect();
+    let mut keys: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, [3, 4, 5, 6]);
+
+    assert_eq!(*F.try_take(&k).unwrap(), "hello");
+    assert!(F.try_get(&k).is_none());
+    assert!(F.try_free(&k2));
+    assert!(!F.try_free(&k2));
+}
+
+#[test]
+fn use_custom_forgotten_rw_lock() {
+    static F: ForgottenRwLock<u8, dyn Send + Sync + std::fmt::Debug> = ForgottenRwLock::new();
+
+    let k = F.forget_arc(Arc::new(1u8));
+    assert_eq!(k, 1);
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| thread::spawn(move || format!("{:?}", F.try_get(&k).unwrap())))
+        .collect();
+    for r in readers {
+        assert_eq!(r.join().unwrap(), "1");
+    }
+
+    assert!(F.try_free(&k));
+    assert!(F.try_take(&k).is_none());
+}
+
+#[test]
+fn use_custom_forgotten_mutex_int_hasher() {
+    use forgotten::BuildIntHasher;
+
+    static F: ForgottenMutex<u64, u64, BuildIntHasher> = ForgottenMutex::new();
+
+    let k = F.forget(42);
+    assert_eq!(*F.try_get(&k).unwrap(), 42);
+}
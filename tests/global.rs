@@ -0,0 +1,836 @@
+use std::rc::Weak;
+
+use forgotten::SharedForgottenKey;
+
+#[test]
+fn use_forget_with() {
+    struct Node {
+        key: SharedForgottenKey<Node>,
+        other: SharedForgottenKey<String>,
+    }
+
+    let k = forgotten::forget_with(|key| Node {
+        key,
+        other: forgotten::forget("other".to_string()).into_shared(),
+    });
+    let node = forgotten::get(&k);
+    assert_eq!(*forgotten::try_get(&node.other).unwrap(), "other");
+    assert_eq!(*node.other.as_usize(), 2);
+
+    let k = k.into_shared();
+    assert_eq!(*k.as_usize(), 1);
+    assert_eq!(node.key, k);
+}
+
+#[test]
+fn use_forget_cyclic() {
+    struct Node {
+        key: SharedForgottenKey<Node>,
+        this: Weak<Node>,
+    }
+
+    let k = forgotten::forget_cyclic(|key, this| Node {
+        key,
+        this: this.clone(),
+    });
+
+    let node = forgotten::get(&k);
+    assert_eq!(node.key, k.into_shared());
+    assert!(std::rc::Rc::ptr_eq(&node.this.upgrade().unwrap(), &node));
+}
+
+#[test]
+fn forget_with_panic_releases_key() {
+    let r = std::panic::catch_unwind(|| {
+        forgotten::forget_with::<u8>(|_| panic!("oops"));
+    });
+    assert!(r.is_err());
+
+    let k = forgotten::forget(1u8).into_shared();
+    assert_eq!(*k.as_usize(), 2);
+    let k = unsafe { SharedForgottenKey::<u8>::from_usize(1) };
+    assert!(forgotten::try_get(&k).is_none());
+}
+
+#[test]
+fn use_counted_key() {
+    use std::{cell::Cell, rc::Rc};
+
+    struct Value(Rc<Cell<bool>>);
+
+    impl Drop for Value {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    let dropped = Rc::new(Cell::new(false));
+
+    let k = forgotten::forget_counted(Value(Rc::clone(&dropped)));
+    let shared = k.to_shared();
+    let k2 = k.clone();
+    assert_eq!(k, k2);
+
+    // only releasing the last handle frees the entry
+    assert!(!forgotten::try_free(&shared));
+    assert!(forgotten::try_take(&shared).is_none());
+    assert!(matches!(
+        forgotten::take_checked(&shared),
+        Err(forgotten::AccessError::Owned)
+    ));
+
+    drop(k);
+    assert!(forgotten::try_get(&shared).is_some());
+
+    let (raw, generation) = k2.into_raw();
+    assert!(unsafe { forgotten::dup_raw(raw, generation) }.is_ok());
+    assert!(unsafe { forgotten::release_raw(raw, generation) }.is_ok());
+    assert!(forgotten::try_get(&shared).is_some());
+    assert!(!dropped.get());
+
+    let k3 = unsafe { forgotten::CountedForgottenKey::<Value>::from_raw(raw, generation) };
+    drop(k3);
+    assert!(forgotten::try_get(&shared).is_none());
+    assert!(dropped.get());
+
+    assert!(unsafe { forgotten::dup_raw(raw, generation) }.is_err());
+    assert!(unsafe { forgotten::release_raw(raw, generation) }.is_err());
+}
+
+#[test]
+fn counted_key_generation() {
+    let a = forgotten::forget_counted(1u8);
+    let b = forgotten::forget_counted(2u8);
+    let shared = a.to_shared();
+    let (n, generation) = a.into_raw();
+    let (m, other) = b.into_raw();
+    assert_ne!(generation, other);
+
+    // Handles of another generation are rejected, as if the key had been reused.
+    assert!(unsafe { forgotten::dup_raw(n, other) }.is_err());
+    assert!(unsafe { forgotten::release_raw(n, other) }.is_err());
+    assert_eq!(*forgotten::try_get(&shared).unwrap(), 1);
+
+    assert!(unsafe { forgotten::release_raw(n, generation) }.is_ok());
+    assert!(unsafe { forgotten::release_raw(m, other) }.is_ok());
+    assert!(forgotten::try_get(&shared).is_none());
+}
+
+#[test]
+fn into_counted() {
+    let k = forgotten::forget("a".to_string()).into_counted();
+    let shared = k.to_shared();
+
+    let ks = vec![k.clone(), k.clone()];
+    drop(k);
+    assert_eq!(*forgotten::try_get(&shared).unwrap(), "a");
+
+    drop(ks);
+    assert!(forgotten::try_get(&shared).is_none());
+
+    let plain = forgotten::forget(1u8).into_shared();
+    assert!(unsafe { forgotten::dup_raw(*plain.as_usize(), 0) }.is_err());
+    assert!(unsafe { forgotten::release_raw(*plain.as_usize(), 0) }.is_err());
+    assert!(forgotten::try_free(&plain));
+}
+
+#[test]
+fn shared_key_try_into_owned() {
+    let k = forgotten::forget("a".to_string());
+    let shared = k.into_shared();
+
+    let owned = shared.try_into_owned().unwrap();
+    assert_eq!(*forgotten::get(&owned), "a");
+    assert_eq!(shared.try_into_owned().unwrap_err(), shared);
+
+    let shared = owned.into_shared();
+    let wrong_type = unsafe { SharedForgottenKey::<u8>::from_usize(*shared.as_usize()) };
+    assert!(wrong_type.try_into_owned().is_err());
+
+    let owned = shared.try_into_owned().unwrap();
+    drop(owned);
+    assert!(forgotten::try_get(&shared).is_none());
+    assert!(shared.try_into_owned().is_err());
+
+    let counted = forgotten::forget_counted(1u8);
+    assert!(counted.to_shared().try_into_owned().is_err());
+}
+
+#[test]
+fn shared_keys_leave_owned_entries() {
+    use forgotten::AccessError;
+
+    let k = forgotten::forget(1u8);
+    let shared = k.to_shared();
+    assert!(!forgotten::try_free(&shared));
+    assert!(forgotten::try_take(&shared).is_none());
+    assert!(forgotten::try_reclaim(&shared).is_none());
+    assert_eq!(
+        forgotten::free_checked(&shared).unwrap_err(),
+        AccessError::Owned
+    );
+    assert_eq!(*forgotten::get(&k), 1);
+
+    drop(k);
+    assert!(forgotten::try_get(&shared).is_none());
+}
+
+#[test]
+fn use_unsized_values() {
+    use std::rc::Rc;
+
+    let k = forgotten::forget_str("hello");
+    let s: Rc<str> = forgotten::get(&k);
+    assert_eq!(&*s, "hello");
+    assert_eq!(Rc::strong_count(&s), 2);
+    assert_eq!(&*forgotten::take(k), "hello");
+    assert_eq!(Rc::strong_count(&s), 1);
+
+    let k = forgotten::forget_slice(vec![1u8, 2, 3]).into_shared();
+    assert_eq!(*forgotten::try_get(&k).unwrap(), [1, 2, 3]);
+
+    let wrong = unsafe { SharedForgottenKey::<str>::from_usize(*k.as_usize()) };
+    assert!(std::panic::catch_unwind(|| forgotten::try_get(&wrong)).is_err());
+
+    assert_eq!(*forgotten::try_take(&k).unwrap(), [1, 2, 3]);
+    assert!(forgotten::try_get(&k).is_none());
+
+    let k = forgotten::forget_rc::<[String]>(Rc::from(["a".to_string(), "b".to_string()]));
+    assert_eq!(forgotten::get(&k).join(","), "a,b");
+}
+
+#[test]
+fn unsized_values_are_dropped() {
+    use std::{cell::Cell, rc::Rc};
+
+    struct Value(Rc<Cell<usize>>);
+
+    impl Drop for Value {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let dropped = Rc::new(Cell::new(0));
+    let values: Vec<_> = (0..3).map(|_| Value(Rc::clone(&dropped))).collect();
+
+    let k = forgotten::forget_slice(values);
+    let v = forgotten::get(&k);
+    drop(k);
+    assert_eq!(dropped.get(), 0);
+    assert_eq!(v.len(), 3);
+    drop(v);
+    assert_eq!(dropped.get(), 3);
+}
+
+#[test]
+fn get_as_trait_object() {
+    use std::rc::Rc;
+
+    trait Render {
+        fn render(&self) -> String;
+    }
+
+    struct Button(&'static str);
+    struct Label(usize);
+
+    impl Render for Button {
+        fn render(&self) -> String {
+            format!("[{}]", self.0)
+        }
+    }
+
+    impl Render for Label {
+        fn render(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    forgotten::register_cast::<Button, dyn Render>(|v| v);
+    forgotten::register_cast::<Label, dyn Render>(|v| v);
+
+    let button = forgotten::forget(Button("ok")).into_shared();
+    let label = forgotten::forget(Label(3)).into_shared();
+    let other = forgotten::forget(1u8).into_shared();
+
+    let rendered: Vec<_> = [button.as_usize(), label.as_usize()]
+        .iter()
+        .map(|n| forgotten::get_as::<dyn Render>(n).unwrap().render())
+        .collect();
+    assert_eq!(rendered, ["[ok]", "3"]);
+
+    assert!(forgotten::get_as::<dyn Render>(other.as_usize()).is_none());
+    assert!(other.cast::<dyn Render>().is_none());
+
+    let keys: Vec<SharedForgottenKey<dyn Render>> =
+        vec![button.cast().unwrap(), label.cast().unwrap()];
+    let v: Rc<dyn Render> = forgotten::try_get_as(&keys[1]).unwrap();
+    assert_eq!(v.render(), "3");
+
+    assert!(forgotten::try_free(&label));
+    assert!(forgotten::try_get_as(&keys[1]).is_none());
+}
+
+#[test]
+fn any_key_round_trip() {
+    use std::collections::HashSet;
+
+    use forgotten::{AnyForgottenKey, SharedAnyForgottenKey};
+
+    let keys: Vec<SharedAnyForgottenKey> = vec![
+        forgotten::forget(1u8).into_shared().into(),
+        forgotten::forget_str("s").into_shared().into(),
+    ];
+    assert_eq!(keys[1].type_name(), "str");
+    assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 2);
+
+    // keys are compared by type as well
+    let n = *keys[0].as_usize();
+    let other: SharedAnyForgottenKey = unsafe { SharedForgottenKey::<u16>::from_usize(n) }.into();
+    assert_ne!(other, keys[0]);
+    assert_eq!(
+        keys.iter()
+            .chain([other].iter())
+            .collect::<HashSet<_>>()
+            .len(),
+        3
+    );
+
+    let k = keys[0].downcast::<u16>().unwrap_err();
+    assert_eq!(k, keys[0]);
+    let k = k.downcast::<u8>().unwrap();
+    assert_eq!(*forgotten::try_get(&k).unwrap(), 1);
+    assert_eq!(
+        &*forgotten::try_get(&keys[1].downcast::<str>().unwrap()).unwrap(),
+        "s"
+    );
+
+    let owned = AnyForgottenKey::from(forgotten::forget(2u8));
+    let shared = unsafe { SharedForgottenKey::<u8>::from_usize(*owned.as_usize()) };
+    let owned = owned.downcast::<String>().unwrap_err();
+    assert!(owned.is::<u8>());
+    drop(owned);
+    assert!(forgotten::try_get(&shared).is_none());
+}
+
+#[test]
+fn stable_type_tags() {
+    use forgotten::StableTypeTag;
+
+    struct Widget(u8);
+    struct Gadget;
+
+    impl StableTypeTag for Widget {
+        const TAG: u64 = forgotten::type_tag("tests::Widget");
+    }
+
+    impl StableTypeTag for Gadget {
+        const TAG: u64 = 7;
+    }
+
+    assert_eq!(forgotten::type_tag(""), 0xcbf2_9ce4_8422_2325);
+    assert_ne!(Widget::TAG, forgotten::type_tag("tests::Gadget"));
+
+    let (tag, n) = forgotten::forget_tagged(Widget(1))
+        .into_shared()
+        .into_tag_and_usize();
+    assert_eq!(tag, Widget::TAG);
+    assert_eq!(forgotten::tag_of(&n), Ok(Some(tag)));
+
+    assert!(SharedForgottenKey::<Gadget>::from_tag_and_usize(tag, n).is_none());
+    assert!(SharedForgottenKey::<Gadget>::from_tag_and_usize(Gadget::TAG, n).is_none());
+    let k = SharedForgottenKey::<Widget>::from_tag_and_usize(tag, n).unwrap();
+    assert_eq!(forgotten::try_get(&k).unwrap().0, 1);
+
+    let untagged = forgotten::forget(Widget(2)).into_shared();
+    assert_eq!(forgotten::tag_of(untagged.as_usize()), Ok(None));
+    assert!(SharedForgottenKey::<Widget>::from_tag_and_usize(tag, *untagged.as_usize()).is_none());
+
+    assert!(forgotten::try_free(&k));
+    assert!(SharedForgottenKey::<Widget>::from_tag_and_usize(tag, n).is_none());
+}
+
+#[test]
+fn free_parent_frees_children() {
+    struct Document;
+    struct Page(u8);
+    struct Image;
+
+    let doc = forgotten::forget(Document);
+    let pages: Vec<_> = (1..=2)
+        .map(|i| forgotten::forget_child(&doc.to_shared(), Page(i)))
+        .collect();
+    let image = forgotten::forget_child(&pages[0], Image);
+
+    let children = forgotten::children(&doc.to_shared()).unwrap();
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[1].downcast::<Page>().unwrap(), pages[1]);
+    assert!(children[0].downcast::<Image>().is_err());
+    assert_eq!(
+        forgotten::children(&pages[0]).unwrap()[0].downcast(),
+        Ok(image)
+    );
+
+    assert!(pages[1].try_into_owned().is_err());
+    assert!(forgotten::try_free(&pages[1]));
+    assert_eq!(forgotten::children(&doc.to_shared()).unwrap().len(), 1);
+
+    assert_eq!(forgotten::try_get(&pages[0]).unwrap().0, 1);
+    drop(doc);
+    assert!(forgotten::try_get(&pages[0]).is_none());
+    assert!(forgotten::try_get(&image).is_none());
+
+    let doc = forgotten::forget(Document);
+    let page = forgotten::forget_child(&doc.to_shared(), Page(3));
+    let _doc = forgotten::take(doc);
+    assert!(forgotten::try_get(&page).is_none());
+}
+
+#[test]
+fn value_may_own_keys() {
+    struct Node(forgotten::ForgottenKey<u8>);
+
+    let k = forgotten::forget(Node(forgotten::forget(1))).into_shared();
+    let inner = forgotten::try_get(&k).unwrap().0.to_shared();
+    assert!(forgotten::try_free(&k));
+    assert!(forgotten::try_get(&inner).is_none());
+}
+
+#[test]
+#[should_panic(expected = "parent of forget_child is not in Forgotten")]
+fn forget_child_of_freed_parent() {
+    let parent = forgotten::forget(0u8).into_shared();
+    forgotten::try_free(&parent);
+    forgotten::forget_child(&parent, 1u8);
+}
+
+#[test]
+fn collect_unreachable_cycles() {
+    use std::cell::Cell;
+
+    use forgotten::{Trace, Tracer};
+
+    struct Node {
+        next: Cell<Option<SharedForgottenKey<Node>>>,
+    }
+
+    impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            if let Some(next) = self.next.get() {
+                tracer.visit_key(&next);
+            }
+        }
+    }
+
+    fn node() -> SharedForgottenKey<Node> {
+        forgotten::forget_traced(Node {
+            next: Cell::new(None),
+        })
+        .into_shared()
+    }
+
+    let link = |a: &SharedForgottenKey<Node>, b: &SharedForgottenKey<Node>| {
+        forgotten::try_get(a).unwrap().next.set(Some(*b));
+    };
+
+    let root = forgotten::forget_traced(Node {
+        next: Cell::new(None),
+    });
+    let (a, b, c) = (node(), node(), node());
+    link(&root.to_shared(), &a);
+    link(&b, &c);
+    link(&c, &b);
+
+    let untraced = forgotten::forget(0u8).into_shared();
+    let pinned = node();
+    assert!(forgotten::pin(&pinned).is_ok());
+    let child = forgotten::forget_child(&a, 1u8);
+
+    let mut freed = vec![*b.as_usize(), *c.as_usize()];
+    freed.sort_unstable();
+    assert_eq!(forgotten::collect(), freed);
+    assert!(forgotten::try_get(&b).is_none());
+    assert!(forgotten::try_get(&a).is_some());
+    assert!(forgotten::try_get(&child).is_some());
+    assert!(forgotten::try_get(&untraced).is_some());
+    assert!(forgotten::try_get(&pinned).is_some());
+
+    forgotten::unpin(&pinned).unwrap();
+    drop(root);
+    let mut freed = vec![*a.as_usize(), *pinned.as_usize(), *child.as_usize()];
+    freed.sort_unstable();
+    assert_eq!(forgotten::collect(), freed);
+    assert!(forgotten::collect().is_empty());
+}
+
+#[test]
+fn transient_entries_expire() {
+    let persistent = forgotten::forget(0u8);
+    let a = forgotten::forget_transient(1u8);
+    let b = forgotten::forget_transient(2u8);
+    assert_eq!(forgotten::current_epoch(), 0);
+
+    assert!(forgotten::try_free(&b));
+    let c = forgotten::forget_transient(3u8);
+
+    assert_eq!(forgotten::advance_epoch(), 2);
+    assert_eq!(forgotten::current_epoch(), 1);
+    assert!(forgotten::try_get(&a).is_none());
+    assert!(forgotten::try_get(&c).is_none());
+    assert_eq!(*forgotten::get(&persistent), 0);
+
+    forgotten::set_epoch_retention(1);
+    let d = forgotten::forget_transient(4u8);
+    let kept = forgotten::forget_transient(5u8).try_into_owned().unwrap();
+    assert_eq!(forgotten::advance_epoch(), 0);
+    assert!(forgotten::try_get(&d).is_some());
+    assert_eq!(forgotten::advance_epoch(), 1);
+    assert!(forgotten::try_get(&d).is_none());
+    assert_eq!(*forgotten::get(&kept), 5);
+}
+
+#[test]
+fn sweep_orphaned_entries() {
+    let (a, rc_a) = forgotten::forget_and_get(1u8);
+    let a = a.into_shared();
+    let (b, rc_b) = forgotten::forget_and_get(2u8);
+    let b = b.into_shared();
+    let s = forgotten::forget_str("s").into_shared();
+    let rc_s = forgotten::try_get(&s).unwrap();
+    let (owned, rc_owned) = forgotten::forget_and_get(3u8);
+
+    for k in [a, b].iter() {
+        assert!(forgotten::set_owned_elsewhere(k, true).is_ok());
+    }
+    assert!(forgotten::set_owned_elsewhere(&s, true).is_ok());
+    assert!(forgotten::set_owned_elsewhere(&owned.to_shared(), true).is_ok());
+
+    assert!(forgotten::sweep_orphans().is_empty());
+
+    drop(rc_a);
+    drop(rc_s);
+    drop(rc_owned);
+    let mut orphans = vec![*a.as_usize(), *s.as_usize()];
+    orphans.sort_unstable();
+    assert_eq!(forgotten::sweep_orphans(), orphans);
+    assert!(forgotten::try_get(&a).is_none());
+    assert_eq!(*forgotten::get(&owned), 3);
+
+    forgotten::set_auto_sweep(2);
+    drop(rc_b);
+    forgotten::forget(0u8);
+    assert!(forgotten::try_get(&b).is_some());
+    forgotten::forget(0u8);
+    assert!(forgotten::try_get(&b).is_none());
+}
+
+#[test]
+fn hooks_are_called_outside_borrows() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+
+    let l = Rc::clone(&log);
+    forgotten::on_insert(move |n, type_name| {
+        l.borrow_mut().push(format!("insert {} {}", n, type_name));
+    });
+    let l = Rc::clone(&log);
+    forgotten::on_remove(move |n| {
+        // the store can be used from hooks
+        let exists = unsafe { forgotten::try_get_with_usize::<u8>(&n) }.is_some();
+        l.borrow_mut().push(format!("remove {} {}", n, exists));
+    });
+    let l = Rc::clone(&log);
+    forgotten::on_value_dropped(move |n| l.borrow_mut().push(format!("drop {}", n)));
+
+    let a = forgotten::forget(1u8);
+    let b = forgotten::forget_child(&a.to_shared(), 2u8);
+    let c = forgotten::forget(3u8);
+    assert_eq!(*forgotten::take(c), 3);
+    drop(a);
+    assert!(forgotten::try_get(&b).is_none());
+
+    assert_eq!(
+        *log.borrow(),
+        [
+            "insert 1 u8",
+            "insert 2 u8",
+            "insert 3 u8",
+            "remove 3 false",
+            "remove 1 false",
+            "remove 2 false",
+            "drop 1",
+            "drop 2",
+        ]
+    );
+
+    forgotten::clear_hooks();
+    forgotten::forget(4u8);
+    assert_eq!(log.borrow().len(), 8);
+}
+
+#[test]
+fn finalizers_run_once() {
+    use forgotten::AccessError;
+    use std::{cell::RefCell, rc::Rc};
+
+    struct Resource(u8);
+
+    let released = Rc::new(RefCell::new(Vec::new()));
+    let finalizer = |released: &Rc<RefCell<Vec<u8>>>| {
+        let released = Rc::clone(released);
+        move |r: Resource| released.borrow_mut().push(r.0)
+    };
+
+    let a = forgotten::forget_with_finalizer(Resource(1), finalizer(&released));
+    drop(a);
+    assert_eq!(*released.borrow(), [1]);
+
+    // the value is not shared, so that the finalizer gets it
+    let b = forgotten::forget_with_finalizer(Resource(2), finalizer(&released)).into_shared();
+    assert!(matches!(
+        forgotten::get_checked(&b),
+        Err(AccessError::Finalized)
+    ));
+    assert!(forgotten::try_get(&b).is_none());
+    assert!(forgotten::try_free(&b));
+    assert_eq!(*released.borrow(), [1, 2]);
+
+    // taking the value back skips the finalizer
+    let c = forgotten::forget_with_finalizer(Resource(3), finalizer(&released));
+    let taken = forgotten::take(c);
+    assert_eq!(taken.0, 3);
+    let d = forgotten::forget_with_finalizer(Resource(4), finalizer(&released));
+    drop(forgotten::reclaim(d));
+    assert_eq!(*released.borrow(), [1, 2]);
+
+    // the store is not borrowed while finalizers run
+    let e = forgotten::forget_with_finalizer(Resource(5), |r: Resource| {
+        forgotten::forget(r.0).into_shared();
+    });
+    forgotten::free(e);
+}
+
+#[test]
+fn free_entries_by_tag() {
+    use forgotten::{Meta, Tag};
+
+    let session = Tag::from("session");
+    let a = forgotten::forget_with_meta(1u8, Meta::new().with_tag("session").with_owner(7));
+    let b =
+        forgotten::forget_with_meta("b".to_string(), Meta::new().with_tag("session").with_tag(2));
+    let c = forgotten::forget_with_meta(3u8, Meta::new().with_tag(2));
+    let (a, b, c) = (a.into_shared(), b.into_shared(), c.into_shared());
+
+    let meta = forgotten::meta(&a).unwrap().unwrap();
+    assert_eq!(meta.tags(), [Tag::from("session")]);
+    assert_eq!(meta.owner(), Some(7));
+    assert_eq!(
+        forgotten::keys_with_tag(&session),
+        [*a.as_usize(), *b.as_usize()]
+    );
+
+    assert!(forgotten::try_free(&b));
+    assert_eq!(forgotten::keys_with_tag(&Tag::Int(2)), [*c.as_usize()]);
+
+    // entries owned by keys are left to them
+    let owned = forgotten::forget_with_meta(4u8, Meta::new().with_tag("session"));
+    let counted = forgotten::forget_with_meta(5u8, Meta::new().with_tag("session")).into_counted();
+
+    assert_eq!(forgotten::free_by_tag(&session), [*a.as_usize()]);
+    assert!(forgotten::try_get(&a).is_none());
+    assert_eq!(
+        forgotten::keys_with_tag(&session),
+        [*owned.to_shared().as_usize(), *counted.as_usize()]
+    );
+    assert_eq!(*forgotten::get(&owned), 4);
+    assert_eq!(*forgotten::try_get(&counted.to_shared()).unwrap(), 5);
+    assert_eq!(*forgotten::try_get(&c).unwrap(), 3);
+
+    drop((owned, counted));
+    assert!(forgotten::keys_with_tag(&session).is_empty());
+}
+
+#[test]
+fn owner_quotas() {
+    use forgotten::{Meta, Quota};
+
+    let owner = 48;
+    forgotten::set_quota(owner, Quota::new().with_max_entries(2));
+
+    let a = forgotten::try_forget_with_meta(1u32, Meta::new().with_owner(owner)).unwrap();
+    let b = forgotten::try_forget_with_meta(2u32, Meta::new().with_owner(owner)).unwrap();
+    assert_eq!(forgotten::usage(owner).entries(), 2);
+    assert_eq!(forgotten::usage(owner).bytes(), 8);
+
+    let e = forgotten::try_forget_with_meta(3u32, Meta::new().with_owner(owner)).unwrap_err();
+    assert_eq!(e.owner(), owner);
+    assert_eq!(e.usage(), forgotten::usage(owner));
+
+    // other owners are not limited
+    let c = forgotten::forget_with_meta(3u32, Meta::new().with_owner(owner + 1));
+
+    forgotten::free(a);
+    assert_eq!(forgotten::usage(owner).entries(), 1);
+
+    forgotten::set_size_of(|v| match v.downcast_ref::<Vec<u8>>() {
+        Some(v) => v.len(),
+        None => std::mem::size_of_val(v),
+    });
+    forgotten::set_quota(owner, Quota::new().with_max_bytes(100));
+    assert!(forgotten::try_forget_with_meta(vec![0u8; 97], Meta::new().with_owner(owner)).is_err());
+    let d = forgotten::try_forget_with_meta(vec![0u8; 96], Meta::new().with_owner(owner)).unwrap();
+    assert_eq!(forgotten::usage(owner).bytes(), 100);
+
+    forgotten::remove_quota(owner);
+    forgotten::free(b);
+    forgotten::free(c);
+    forgotten::free(d);
+    assert_eq!(forgotten::usage(owner), Default::default());
+}
+
+#[test]
+fn sandboxes_revoke_aliases() {
+    use forgotten::{Caps, Sandbox};
+
+    let k = forgotten::forget("object".to_string());
+    let plugin = Sandbox::new();
+    let other = Sandbox::new();
+
+    let a = plugin
+        .grant_with_caps(&k.to_shared(), Caps::READ | Caps::DERIVE)
+        .unwrap();
+    let b = plugin.grant(&a).unwrap();
+    let c = other
+        .grant_with_caps(&k.to_shared(), Caps::READ | Caps::DERIVE)
+        .unwrap();
+    assert_ne!(a.as_usize(), k.to_shared().as_usize());
+    assert_eq!(*forgotten::try_get(&b).unwrap(), "object");
+
+    // aliases can not free the entry
+    assert!(!forgotten::try_free(&a));
+    assert!(!other.revoke(&a));
+
+    assert!(plugin.revoke(&b));
+    assert!(forgotten::try_get(&b).is_none());
+    assert_eq!(plugin.revoke_all(), 1);
+    assert!(forgotten::try_get(&a).is_none());
+    assert_eq!(*forgotten::try_get(&c).unwrap(), "object");
+    assert_eq!(*forgotten::get(&k), "object");
+
+    let d = plugin.grant(&c).unwrap();
+    assert!(plugin.grant(&d).is_none());
+    drop(plugin);
+    assert!(forgotten::try_get(&d).is_none());
+
+    // aliases derived from a granted one are revoked along with it
+    let nested = Sandbox::new();
+    let e = c.restrict(Caps::READ).unwrap();
+    let f = nested.grant(&c).unwrap();
+    assert_eq!(*forgotten::try_get(&f).unwrap(), "object");
+    assert_eq!(other.revoke_all(), 3);
+    assert!(forgotten::try_get(&e).is_none());
+    assert!(forgotten::try_get(&f).is_none());
+    assert_eq!(nested.revoke_all(), 0);
+
+    let g = other
+        .grant_with_caps(&k.to_shared(), Caps::READ | Caps::DERIVE)
+        .unwrap();
+    forgotten::free(k);
+    assert!(forgotten::try_get(&g).is_none());
+    assert_eq!(other.revoke_all(), 0);
+    assert!(other.grant(&g).is_none());
+}
+
+#[test]
+fn restricted_keys() {
+    use forgotten::{AccessError, Caps, Sandbox};
+
+    let k = forgotten::forget(5u8).into_shared();
+    assert_eq!(forgotten::caps(&k), Some(Caps::ALL));
+
+    let read = k.restrict(Caps::READ).unwrap();
+    assert_eq!(forgotten::caps(&read), Some(Caps::READ));
+    assert_eq!(*forgotten::get_checked(&read).unwrap(), 5);
+    assert!(forgotten::try_take(&read).is_none());
+    assert_eq!(
+        forgotten::free_checked(&read).unwrap_err().required(),
+        Some(Caps::FREE)
+    );
+    assert!(read.restrict(Caps::READ).is_err());
+
+    // every operation checks the capabilities of the key
+    assert_eq!(forgotten::meta(&read), Ok(None));
+    assert_eq!(forgotten::tag_of(read.as_usize()), Ok(None));
+    assert!(forgotten::children(&read).unwrap().is_empty());
+    assert!(forgotten::pin(&read).is_err());
+    assert!(forgotten::set_owned_elsewhere(&read, true).is_err());
+    assert_eq!(
+        forgotten::try_forget_child(&read, 0u8)
+            .unwrap_err()
+            .required(),
+        Some(Caps::DERIVE)
+    );
+
+    // restricting never adds capabilities
+    let derive = k.restrict(Caps::DERIVE | Caps::FREE).unwrap();
+    let free = derive.restrict(Caps::ALL).unwrap();
+    assert_eq!(forgotten::caps(&free), Some(Caps::DERIVE | Caps::FREE));
+    assert!(forgotten::get_checked(&free).is_err());
+
+    let plugin = Sandbox::new();
+    let granted = plugin.grant_with_caps(&k, Caps::TAKE).unwrap();
+    assert!(plugin.grant(&read).is_none());
+
+    assert_eq!(*forgotten::take_checked(&granted).unwrap(), 5);
+    assert!(forgotten::try_get(&k).is_none());
+    assert!(forgotten::caps(&read).is_none());
+    // a revoked alias is told apart from one lacking capabilities
+    assert_eq!(
+        forgotten::get_checked(&read).unwrap_err(),
+        AccessError::NotFound
+    );
+    assert_eq!(
+        forgotten::free_checked(&k).unwrap_err(),
+        AccessError::NotFound
+    );
+
+    // dropped aliases are revoked in their sandboxes too
+    let k = forgotten::forget(6u8).into_shared();
+    let granted = plugin
+        .grant_with_caps(&k, Caps::READ | Caps::DERIVE)
+        .unwrap();
+    let alias = granted.restrict(Caps::READ).unwrap();
+    assert!(granted.unrestrict());
+    assert!(!plugin.revoke(&granted));
+    assert!(!k.unrestrict());
+    assert_eq!(*forgotten::try_get(&alias).unwrap(), 6);
+    assert!(alias.unrestrict());
+    assert!(forgotten::caps(&alias).is_none());
+    assert_eq!(plugin.revoke_all(), 0);
+
+    let free = k.restrict(Caps::FREE).unwrap();
+    assert!(forgotten::free_checked(&free).is_ok());
+    assert!(forgotten::try_get(&k).is_none());
+
+    // aliases of owned entries can not remove them either
+    let owned = forgotten::forget(7u8);
+    let alias = owned.to_shared().restrict(Caps::FREE | Caps::TAKE).unwrap();
+    let granted = plugin
+        .grant_with_caps(&owned.to_shared(), Caps::FREE | Caps::TAKE)
+        .unwrap();
+    for k in [&alias, &granted] {
+        assert_eq!(forgotten::free_checked(k).unwrap_err(), AccessError::Owned);
+        assert_eq!(forgotten::take_checked(k).unwrap_err(), AccessError::Owned);
+        assert!(!forgotten::try_free(k));
+    }
+    assert_eq!(*forgotten::get(&owned), 7);
+
+    let counted = forgotten::forget_counted(8u8);
+    let alias = counted.to_shared().restrict(Caps::ALL).unwrap();
+    assert_eq!(
+        forgotten::free_checked(&alias).unwrap_err(),
+        AccessError::Owned
+    );
+    assert_eq!(*forgotten::try_get(&counted.to_shared()).unwrap(), 8);
+}
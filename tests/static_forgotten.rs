@@ -0,0 +1,44 @@
+use forgotten::StaticForgotten;
+
+#[test]
+fn use_static_forgotten() {
+    let mut f = StaticForgotten::<String, 3>::new();
+
+    assert_eq!(f.forget("a".to_string()).unwrap(), 1);
+    assert_eq!(f.forget("b".to_string()).unwrap(), 2);
+    assert_eq!(f.forget("c".to_string()).unwrap(), 3);
+    assert_eq!(f.len(), 3);
+
+    let full = f.forget("d".to_string()).unwrap_err();
+    assert_eq!(full.into_inner(), "d");
+
+    assert_eq!(f.try_get(&2).unwrap(), "b");
+    assert!(f.try_get(&0).is_none());
+    assert!(f.try_get(&4).is_none());
+
+    f.try_get_mut(&2).unwrap().push('!');
+    assert_eq!(f.try_take(&2).unwrap(), "b!");
+    assert!(f.try_take(&2).is_none());
+    assert!(f.try_get(&2).is_none());
+
+    assert_eq!(f.forget("e".to_string()).unwrap(), 2);
+
+    assert!(f.try_free(&1));
+    assert!(!f.try_free(&1));
+    assert_eq!(f.len(), 2);
+
+    assert_eq!(f.forget("f".to_string()).unwrap(), 1);
+    assert_eq!(f.try_get(&1).unwrap(), "f");
+    assert_eq!(f.try_get(&3).unwrap(), "c");
+}
+
+#[test]
+fn use_static_forgotten_in_static() {
+    use std::sync::Mutex;
+
+    static F: Mutex<StaticForgotten<u32, 2>> = Mutex::new(StaticForgotten::new());
+
+    let k = F.lock().unwrap().forget(42).unwrap();
+    assert_eq!(F.lock().unwrap().try_get(&k), Some(&42));
+    assert_eq!(F.lock().unwrap().try_take(&k), Some(42));
+}